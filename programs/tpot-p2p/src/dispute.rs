@@ -1,27 +1,105 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
-use crate::{EscrowAccount, Order, PlatformConfig};
+use crate::{EscrowAccount, P2PError, PlatformConfig, ReputationHistory, Trade, UserProfile};
+
+pub const MAX_EVIDENCE_PER_PARTY: usize = 5;
+pub const MAX_EVIDENCE_LABEL_LEN: usize = 32;
+pub const APPEAL_PANEL_SIZE: usize = 3;
 
 #[account]
 pub struct Dispute {
     pub dispute_id: bool,
     pub order_id: u64,
+    pub trade_id: u64,
     pub plaintiff: Pubkey,
     pub defendant: Pubkey,
     pub arbitrator: Pubkey,
     pub reason: String,
-    pub evidence_hashes: Vec<[u8; 32]>,
+    pub evidence: Vec<Evidence>,
     pub status: DisputeStatus,
     pub ruling: Option<Ruling>,
     pub created_at: i64,
+    pub response_deadline: i64,
+    pub appeal_deadline: i64,
     pub resolved_at: Option<i64>,
+    pub bond_amount: u64,
+    pub bond_in_sol: bool,
+    pub bond_status: BondStatus,
+    pub appealed: bool,
+    pub panel_review: bool,
     pub bump: u8,
 }
 
 impl Dispute {
-    pub const SIZE: usize =
-        1 + 8 + 32 + 32 + 32 + 4 + 200 + 4 + (32 * 10) + 1 + 1 + 1 + 8 + 1 + 8 + 1;
+    pub const SIZE: usize = 1 +     // dispute_id
+        8 +                         // order_id
+        8 +                         // trade_id
+        32 +                        // plaintiff
+        32 +                        // defendant
+        32 +                        // arbitrator
+        4 + 200 +                   // reason
+        4 + Evidence::SIZE * MAX_EVIDENCE_PER_PARTY * 2 + // evidence
+        1 +                         // status
+        1 + Ruling::SIZE +          // ruling
+        8 +                         // created_at
+        8 +                         // response_deadline
+        8 +                         // appeal_deadline
+        1 + 8 +                     // resolved_at
+        8 +                         // bond_amount
+        1 +                         // bond_in_sol
+        1 +                         // bond_status
+        1 +                         // appealed
+        1 +                         // panel_review
+        1; // bump
+
+    /// 某一方已提交的证据数量
+    pub fn evidence_count(&self, submitter: &Pubkey) -> usize {
+        self.evidence
+            .iter()
+            .filter(|e| e.submitter == *submitter)
+            .count()
+    }
+}
+
+/// 争议证据
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    pub submitter: Pubkey,
+    pub label: String,
+    pub hash: [u8; 32],
+    pub submitted_at: i64,
+}
+
+impl Evidence {
+    pub const SIZE: usize = 32 + 4 + MAX_EVIDENCE_LABEL_LEN + 32 + 8;
+}
+
+/// 仲裁员登记
+#[account]
+pub struct Arbitrator {
+    pub wallet: Pubkey,
+    pub max_amount: u64,
+    pub status: ArbitratorStatus,
+    pub active_cases: u32,
+    pub resolved_cases: u32,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Arbitrator {
+    pub const SIZE: usize = 32 + 8 + 1 + 4 + 4 + 8 + 1;
+
+    /// 是否可受理该金额的争议
+    pub fn is_eligible(&self, amount: u64) -> bool {
+        self.status == ArbitratorStatus::Active && amount <= self.max_amount
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ArbitratorStatus {
+    Active,
+    Suspended,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -29,73 +107,815 @@ pub enum DisputeStatus {
     Opened,
     EvidencePhase,
     Arbitrating,
+    Ruled,    // 已裁决, 上诉期内
+    Appealed, // 上诉合议中
     Resolved,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+/// 争议保证金状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BondStatus {
+    Held,      // 托管中
+    Refunded,  // 发起方胜诉, 已退还
+    Forfeited, // 发起方败诉, 已没收 (代币归国库, SOL 归指派仲裁员)
+}
+
+/// 上诉
+#[account]
+pub struct Appeal {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub appellant: Pubkey,
+    pub original_ruling: Ruling,
+    pub bond_amount: u64,
+    pub vote_bond: u64,
+    pub panel: Vec<Pubkey>,
+    pub votes: Vec<PanelVote>,
+    pub status: AppealStatus,
+    pub final_ruling: Option<Ruling>,
+    pub created_at: i64,
+    pub commit_deadline: i64,
+    pub reveal_deadline: i64,
+    pub bump: u8,
+}
+
+impl Appeal {
+    pub const SIZE: usize = 8 +     // order_id
+        8 +                         // trade_id
+        32 +                        // appellant
+        Ruling::SIZE +              // original_ruling
+        8 +                         // bond_amount
+        8 +                         // vote_bond
+        4 + 32 * APPEAL_PANEL_SIZE + // panel
+        4 + PanelVote::SIZE * APPEAL_PANEL_SIZE + // votes
+        1 +                         // status
+        1 + Ruling::SIZE +          // final_ruling
+        8 +                         // created_at
+        8 +                         // commit_deadline
+        8 +                         // reveal_deadline
+        1; // bump
+
+    pub fn is_panelist(&self, key: &Pubkey) -> bool {
+        self.panel.contains(key)
+    }
+
+    pub fn has_committed(&self, key: &Pubkey) -> bool {
+        self.votes.iter().any(|v| v.arbitrator == *key)
+    }
+
+    pub fn has_commitment(&self, commitment: &[u8; 32]) -> bool {
+        self.votes.iter().any(|v| v.commitment == *commitment)
+    }
+
+    /// 全员已提交承诺或承诺期已过, 即可开始揭示
+    pub fn reveal_open(&self, now: i64) -> bool {
+        (self.votes.len() == self.panel.len() || now >= self.commit_deadline)
+            && now < self.reveal_deadline
+    }
+
+    /// 揭示期已过, 或承诺期结束后所有承诺均已揭示, 即可计票
+    pub fn voting_closed(&self, now: i64) -> bool {
+        let all_revealed = self.votes.iter().all(|v| v.ruling.is_some());
+        now >= self.reveal_deadline
+            || (all_revealed
+                && (self.votes.len() == self.panel.len() || now >= self.commit_deadline))
+    }
+
+    /// 已揭示投票中获得过半数 (按合议庭人数) 的裁决
+    pub fn majority(&self) -> Option<Ruling> {
+        self.votes.iter().filter_map(|v| v.ruling).find(|ruling| {
+            self.votes
+                .iter()
+                .filter(|v| v.ruling == Some(*ruling))
+                .count()
+                > self.panel.len() / 2
+        })
+    }
+}
+
+/// 合议庭投票 (承诺-揭示)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PanelVote {
+    pub arbitrator: Pubkey,
+    pub commitment: [u8; 32],   // blake3(ruling || salt)
+    pub ruling: Option<Ruling>, // 揭示后的裁决
+}
+
+impl PanelVote {
+    pub const SIZE: usize = 32 + 32 + 1 + Ruling::SIZE;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AppealStatus {
+    Voting,  // 投票中
+    Decided, // 已形成多数意见
+    Expired, // 计票未形成多数, 维持原裁决
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum Ruling {
     FavorBuyer,
     FavorSeller,
     Split,
+    Custom { buyer_bps: u16 }, // 买方所得比例 (基点), 其余归卖方
+}
+
+impl Ruling {
+    pub const SIZE: usize = 1 + 2;
+    pub const MAX_BPS: u16 = 10000;
+
+    /// 买方所得比例 (基点)
+    pub fn buyer_bps(&self) -> u16 {
+        match self {
+            Ruling::FavorBuyer => Self::MAX_BPS,
+            Ruling::FavorSeller => 0,
+            Ruling::Split => Self::MAX_BPS / 2,
+            Ruling::Custom { buyer_bps } => *buyer_bps,
+        }
+    }
+
+    /// 自定义比例不得超过 100%
+    pub fn is_valid(&self) -> bool {
+        self.buyer_bps() <= Self::MAX_BPS
+    }
+
+    /// 某一方所得比例 (基点)
+    pub fn party_bps(&self, is_buyer: bool) -> u16 {
+        if is_buyer {
+            self.buyer_bps()
+        } else {
+            Self::MAX_BPS - self.buyer_bps()
+        }
+    }
+
+    /// 投票承诺: blake3(borsh(ruling) || panelist || order_id || trade_id || salt)
+    /// 绑定投票人与争议, 抄袭他人承诺无法通过揭示校验
+    pub fn commitment(
+        &self,
+        panelist: &Pubkey,
+        order_id: u64,
+        trade_id: u64,
+        salt: &[u8; 32],
+    ) -> Result<[u8; 32]> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.try_to_vec()?);
+        hasher.update(panelist.as_ref());
+        hasher.update(&order_id.to_le_bytes());
+        hasher.update(&trade_id.to_le_bytes());
+        hasher.update(salt);
+        Ok(hasher.finalize().into())
+    }
+
+    /// 按比例拆分托管余额, 向下取整的零头归卖方
+    pub fn split(&self, balance: u64) -> (u64, u64) {
+        let buyer_share =
+            (balance as u128 * self.buyer_bps() as u128 / Self::MAX_BPS as u128) as u64;
+        (buyer_share, balance - buyer_share)
+    }
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
+#[instruction(order_id: u64, trade_id: u64)]
 pub struct OpenDispute<'info> {
     #[account(
         mut,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump = order.bump
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
     )]
-    pub order: Account<'info, Order>,
+    pub trade: Account<'info, Trade>,
     #[account(
         init,
         payer = disputer,
         space = 8 + Dispute::SIZE,
-        seeds = [b"dispute", order_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
         bump
     )]
-    pub dispute: Account<'info, Dispute>,
+    pub dispute: Box<Account<'info, Dispute>>,
+    #[account(
+        init,
+        payer = disputer,
+        token::mint = token_mint,
+        token::authority = dispute,
+        seeds = [
+            b"dispute_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = disputer_token_account.owner == disputer.key()
+            @ P2PError::BondTokenAccountMismatch,
+        constraint = disputer_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub disputer_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(address = trade.token_mint @ P2PError::InvalidTokenMint)]
+    pub token_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.maker.as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.maker.as_ref()],
+        bump = maker_history.bump
+    )]
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.taker.as_ref()],
+        bump
+    )]
+    pub taker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.taker.as_ref()],
+        bump = taker_history.bump
+    )]
+    pub taker_history: Box<Account<'info, ReputationHistory>>,
     #[account(mut)]
     pub disputer: Signer<'info>,
     #[account(
+        mut,
+        seeds = [b"arbitrator", arbitrator.wallet.as_ref()],
+        bump = arbitrator.bump
+    )]
+    pub arbitrator: Account<'info, Arbitrator>,
+    #[account(
+        mut,
         seeds = [b"platform_config"],
         bump = platform_config.bump
     )]
     pub platform_config: Account<'info, PlatformConfig>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
+#[instruction(order_id: u64, trade_id: u64)]
 pub struct ResolveDispute<'info> {
+    #[account(
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Account<'info, Trade>,
     #[account(
         mut,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump = order.bump
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
     )]
-    pub order: Account<'info, Order>,
+    pub dispute: Account<'info, Dispute>,
+    pub arbitrator: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"dispute", order_id.to_le_bytes().as_ref()],
+        seeds = [b"arbitrator", arbitrator.key().as_ref()],
+        bump = arbitrator_account.bump
+    )]
+    pub arbitrator_account: Account<'info, Arbitrator>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct SubmitEvidence<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    pub submitter: Signer<'info>,
+    #[account(
+        seeds = [b"profile", submitter.key().as_ref()],
+        bump = submitter_profile.bump,
+        constraint = !submitter_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub submitter_profile: Account<'info, UserProfile>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct StartArbitration<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
         bump = dispute.bump
     )]
     pub dispute: Account<'info, Dispute>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct ConvenePanel<'info> {
+    #[account(
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Box<Account<'info, Dispute>>,
+    #[account(
+        init,
+        payer = convener,
+        space = 8 + Appeal::SIZE,
+        seeds = [
+            b"appeal",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub appeal: Box<Account<'info, Appeal>>,
+    #[account(
+        init,
+        payer = convener,
+        token::mint = token_mint,
+        token::authority = dispute,
+        seeds = [
+            b"appeal_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub appeal_bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = convener,
+        token::mint = token_mint,
+        token::authority = dispute,
+        seeds = [
+            b"vote_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub vote_bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(address = trade.token_mint @ P2PError::InvalidTokenMint)]
+    pub token_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"arbitrator", dispute.arbitrator.as_ref()],
+        bump = arbitrator.bump
+    )]
+    pub arbitrator: Account<'info, Arbitrator>,
+    #[account(mut)]
+    pub convener: Signer<'info>,
+    #[account(
+        seeds = [b"profile", convener.key().as_ref()],
+        bump = convener_profile.bump,
+        constraint = !convener_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub convener_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct AppealDispute<'info> {
+    #[account(
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
     #[account(
         mut,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Box<Account<'info, Dispute>>,
+    #[account(
+        init,
+        payer = appellant,
+        space = 8 + Appeal::SIZE,
+        seeds = [
+            b"appeal",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub appeal: Box<Account<'info, Appeal>>,
+    #[account(
+        init,
+        payer = appellant,
+        token::mint = token_mint,
+        token::authority = dispute,
+        seeds = [
+            b"appeal_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub appeal_bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = appellant,
+        token::mint = token_mint,
+        token::authority = dispute,
+        seeds = [
+            b"vote_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub vote_bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = appellant_token_account.owner == appellant.key()
+            @ P2PError::BondTokenAccountMismatch,
+        constraint = appellant_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub appellant_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(address = trade.token_mint @ P2PError::InvalidTokenMint)]
+    pub token_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub appellant: Signer<'info>,
+    #[account(
+        seeds = [b"profile", appellant.key().as_ref()],
+        bump = appellant_profile.bump,
+        constraint = !appellant_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub appellant_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct CastVote<'info> {
+    #[account(
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Box<Account<'info, Dispute>>,
+    #[account(
+        mut,
+        seeds = [
+            b"appeal",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = appeal.bump
+    )]
+    pub appeal: Box<Account<'info, Appeal>>,
+    #[account(
+        mut,
+        seeds = [
+            b"vote_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = vote_bond_vault.owner == dispute.key() @ P2PError::BondTokenAccountMismatch
+    )]
+    pub vote_bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = panelist_token_account.owner == panelist.key()
+            @ P2PError::BondTokenAccountMismatch,
+        constraint = panelist_token_account.mint == vote_bond_vault.mint
+            @ P2PError::InvalidTokenMint
+    )]
+    pub panelist_token_account: Box<Account<'info, TokenAccount>>,
+    pub panelist: Signer<'info>,
+    #[account(
+        seeds = [b"arbitrator", panelist.key().as_ref()],
+        bump = arbitrator.bump
+    )]
+    pub arbitrator: Box<Account<'info, Arbitrator>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct FinalizeDispute<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Box<Account<'info, Dispute>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
         bump = escrow.bump
     )]
-    pub escrow: Account<'info, EscrowAccount>,
-    pub arbitrator: Signer<'info>,
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.maker.as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.maker.as_ref()],
+        bump = maker_history.bump
+    )]
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.taker.as_ref()],
+        bump
+    )]
+    pub taker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.taker.as_ref()],
+        bump = taker_history.bump
+    )]
+    pub taker_history: Box<Account<'info, ReputationHistory>>,
     #[account(
         seeds = [b"platform_config"],
         bump = platform_config.bump
     )]
     pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = escrow_token_account.owner == escrow.key()
+            @ P2PError::EscrowTokenAccountMismatch,
+        constraint = escrow_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == trade.buyer()
+            @ P2PError::BuyerTokenAccountMismatch,
+        constraint = buyer_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub buyer_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == trade.seller()
+            @ P2PError::SellerTokenAccountMismatch,
+        constraint = seller_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub seller_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = bond_vault.owner == dispute.key() @ P2PError::BondTokenAccountMismatch
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"treasury", platform_config.key().as_ref(), trade.token_mint.as_ref()],
+        bump,
+        constraint = treasury.owner == platform_config.key() @ P2PError::FeeTokenAccountMismatch
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"appeal",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = appeal.bump
+    )]
+    pub appeal: Option<Box<Account<'info, Appeal>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"appeal_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = appeal_bond_vault.owner == dispute.key() @ P2PError::BondTokenAccountMismatch
+    )]
+    pub appeal_bond_vault: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"vote_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = vote_bond_vault.owner == dispute.key() @ P2PError::BondTokenAccountMismatch
+    )]
+    pub vote_bond_vault: Option<Box<Account<'info, TokenAccount>>>,
+    // 以 SOL 缴纳的保证金: 胜诉退还发起方, 败诉支付给指派仲裁员
+    #[account(mut, address = dispute.plaintiff @ P2PError::NotParticipant)]
+    pub plaintiff: Option<SystemAccount<'info>>,
+    #[account(mut, address = dispute.arbitrator @ P2PError::NotArbitrator)]
+    pub arbitrator_wallet: Option<SystemAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AddArbitrator<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Arbitrator::SIZE,
+        seeds = [b"arbitrator", wallet.as_ref()],
+        bump
+    )]
+    pub arbitrator: Account<'info, Arbitrator>,
     #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveArbitrator<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"arbitrator", arbitrator.wallet.as_ref()],
+        bump = arbitrator.bump
+    )]
+    pub arbitrator: Account<'info, Arbitrator>,
     #[account(mut)]
-    pub winner_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateArbitrator<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"arbitrator", arbitrator.wallet.as_ref()],
+        bump = arbitrator.bump
+    )]
+    pub arbitrator: Account<'info, Arbitrator>,
+    pub authority: Signer<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_rounds_buyer_down_and_gives_dust_to_seller() {
+        let ruling = Ruling::Custom { buyer_bps: 7001 };
+        assert_eq!(ruling.split(10001), (7001, 3000));
+        assert_eq!(Ruling::Split.split(7), (3, 4));
+    }
+
+    #[test]
+    fn split_edges_move_whole_balance() {
+        for ruling in [Ruling::FavorSeller, Ruling::Custom { buyer_bps: 0 }] {
+            assert_eq!(ruling.split(u64::MAX), (0, u64::MAX));
+        }
+        for ruling in [
+            Ruling::FavorBuyer,
+            Ruling::Custom {
+                buyer_bps: Ruling::MAX_BPS,
+            },
+        ] {
+            assert_eq!(ruling.split(u64::MAX), (u64::MAX, 0));
+        }
+    }
+
+    #[test]
+    fn rejects_custom_share_above_max_bps() {
+        let max = Ruling::Custom {
+            buyer_bps: Ruling::MAX_BPS,
+        };
+        let over = Ruling::Custom {
+            buyer_bps: Ruling::MAX_BPS + 1,
+        };
+        assert!(max.is_valid());
+        assert!(!over.is_valid());
+    }
+
+    #[test]
+    fn commitment_binds_panelist_and_trade() {
+        let salt = [7u8; 32];
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ruling = Ruling::FavorBuyer;
+        let committed = ruling.commitment(&a, 1, 2, &salt).unwrap();
+        assert_eq!(committed, ruling.commitment(&a, 1, 2, &salt).unwrap());
+        assert_ne!(committed, ruling.commitment(&b, 1, 2, &salt).unwrap());
+        assert_ne!(committed, ruling.commitment(&a, 1, 3, &salt).unwrap());
+        assert_ne!(committed, ruling.commitment(&a, 2, 2, &salt).unwrap());
+    }
 }
@@ -1,25 +1,186 @@
 use anchor_lang::prelude::*;
 
+pub const MAX_ARBITRATORS: usize = 10;
+
 // ============ 账户结构 ============
 
 /// 平台配置
 #[account]
+#[derive(Default)]
 pub struct PlatformConfig {
-    pub authority: Pubkey, // 管理员
-    pub platform_fee: u64, // 平台手续费 (基点)
-    pub dispute_fee: u64,  // 争议手续费 (基点)
-    pub paused: bool,      // 是否暂停
-    pub bump: u8,          // PDA bump
+    pub authority: Pubkey,                 // 管理员
+    pub pending_authority: Option<Pubkey>, // 待接受的新管理员
+    pub pauser: Pubkey,                    // 暂停权限
+    pub fee_manager: Pubkey,               // 手续费管理
+    pub arbitrators: Vec<Pubkey>,          // 仲裁员名册 (轮询顺序)
+    pub arbitrator_cursor: u32,            // 下一个轮询位置
+    pub dispute_fee: u64,                  // 争议手续费 (基点)
+    pub dispute_sol_bond: u64,             // 以 SOL 缴纳的争议保证金 (lamports)
+    pub paused: bool,                      // 是否暂停
+    pub auto_release_window: i64,          // 付款后自动放行时限 (秒)
+    pub next_order_id: u64,                // 下一个订单ID
+    pub fee_schedule: [u64; 5],            // 各等级手续费 (基点, Lv1-Lv5)
+    pub order_expiry: i64,                 // 挂单有效期 (秒)
+    pub min_payment_window: i64,           // 最短付款时限 (秒)
+    pub max_payment_window: i64,           // 最长付款时限 (秒)
+    pub min_order_amount: u64,             // 最小挂单数量
+    pub max_order_amount: u64,             // 最大挂单数量
+    pub dispute_response_window: i64,      // 被诉方举证时限 (秒)
+    pub dispute_penalty: u32,              // 争议败诉扣除信誉分
+    pub appeal_window: i64,                // 裁决后上诉期 (秒, 0 为不可上诉)
+    pub appeal_fee: u64,                   // 上诉保证金 (基点)
+    pub appeal_commit_window: i64,         // 合议庭提交投票承诺时限 (秒)
+    pub appeal_reveal_window: i64,         // 合议庭揭示投票时限 (秒)
+    pub vote_bond_fee: u64,                // 合议庭投票保证金 (基点)
+    pub reputation_base_gain: u32,         // 每个成交量级的基础信誉分
+    pub reputation_volume_unit: u64,       // 成交量级单位 (按 log2 计)
+    pub reputation_daily_cap: u32,         // 每日信誉增长上限
+    pub reputation_half_life: i64,         // 信誉衰减半衰期 (秒, 0 为不衰减)
+    pub panel_threshold: u64,              // 首审即由合议庭裁决的成交数量门槛
+    pub rating_window: i64,                // 成交完成后的评价期 (秒), 期内不可关闭成交
+    pub bump: u8,                          // PDA bump
 }
 
 impl PlatformConfig {
-    pub const SIZE: usize = 32 + 8 + 8 + 1 + 1;
+    pub const SIZE: usize = 32 +    // authority
+        1 + 32 +                    // pending_authority
+        32 +                        // pauser
+        32 +                        // fee_manager
+        4 + 32 * MAX_ARBITRATORS +  // arbitrators
+        4 +                         // arbitrator_cursor
+        8 +                         // dispute_fee
+        8 +                         // dispute_sol_bond
+        1 +                         // paused
+        8 +                         // auto_release_window
+        8 +                         // next_order_id
+        8 * 5 +                     // fee_schedule
+        8 +                         // order_expiry
+        8 +                         // min_payment_window
+        8 +                         // max_payment_window
+        8 +                         // min_order_amount
+        8 +                         // max_order_amount
+        8 +                         // dispute_response_window
+        4 +                         // dispute_penalty
+        8 +                         // appeal_window
+        8 +                         // appeal_fee
+        8 +                         // appeal_commit_window
+        8 +                         // appeal_reveal_window
+        8 +                         // vote_bond_fee
+        4 +                         // reputation_base_gain
+        8 +                         // reputation_volume_unit
+        4 +                         // reputation_daily_cap
+        8 +                         // reputation_half_life
+        8 +                         // panel_threshold
+        8 +                         // rating_window
+        1; // bump
+
+    pub fn is_arbitrator(&self, key: &Pubkey) -> bool {
+        self.arbitrators.contains(key)
+    }
+
+    /// 当前可调参数快照
+    pub fn params(&self) -> ConfigParams {
+        ConfigParams {
+            dispute_fee: self.dispute_fee,
+            dispute_sol_bond: self.dispute_sol_bond,
+            order_expiry: self.order_expiry,
+            min_payment_window: self.min_payment_window,
+            max_payment_window: self.max_payment_window,
+            auto_release_window: self.auto_release_window,
+            min_order_amount: self.min_order_amount,
+            max_order_amount: self.max_order_amount,
+            dispute_response_window: self.dispute_response_window,
+            dispute_penalty: self.dispute_penalty,
+            appeal_window: self.appeal_window,
+            appeal_fee: self.appeal_fee,
+            appeal_commit_window: self.appeal_commit_window,
+            appeal_reveal_window: self.appeal_reveal_window,
+            vote_bond_fee: self.vote_bond_fee,
+            reputation_base_gain: self.reputation_base_gain,
+            reputation_volume_unit: self.reputation_volume_unit,
+            reputation_daily_cap: self.reputation_daily_cap,
+            reputation_half_life: self.reputation_half_life,
+            panel_threshold: self.panel_threshold,
+            rating_window: self.rating_window,
+        }
+    }
+
+    pub fn apply_params(&mut self, params: &ConfigParams) {
+        self.dispute_fee = params.dispute_fee;
+        self.dispute_sol_bond = params.dispute_sol_bond;
+        self.order_expiry = params.order_expiry;
+        self.min_payment_window = params.min_payment_window;
+        self.max_payment_window = params.max_payment_window;
+        self.auto_release_window = params.auto_release_window;
+        self.min_order_amount = params.min_order_amount;
+        self.max_order_amount = params.max_order_amount;
+        self.dispute_response_window = params.dispute_response_window;
+        self.dispute_penalty = params.dispute_penalty;
+        self.appeal_window = params.appeal_window;
+        self.appeal_fee = params.appeal_fee;
+        self.appeal_commit_window = params.appeal_commit_window;
+        self.appeal_reveal_window = params.appeal_reveal_window;
+        self.vote_bond_fee = params.vote_bond_fee;
+        self.reputation_base_gain = params.reputation_base_gain;
+        self.reputation_volume_unit = params.reputation_volume_unit;
+        self.reputation_daily_cap = params.reputation_daily_cap;
+        self.reputation_half_life = params.reputation_half_life;
+        self.panel_threshold = params.panel_threshold;
+        self.rating_window = params.rating_window;
+    }
+
+    /// 成交信誉增长: 基础分 × log2(成交量级 + 1), 与同一对手方的既往成交次数越多增长越少
+    pub fn trade_reputation_gain(&self, amount: u64, prior_trades: u32) -> u32 {
+        let units = amount / self.reputation_volume_unit.max(1);
+        let steps = units.saturating_add(1).ilog2();
+        self.reputation_base_gain.saturating_mul(steps) / prior_trades.saturating_add(1)
+    }
+
+    /// 按信誉等级获取手续费率
+    pub fn fee_rate_for_level(&self, level: u8) -> u64 {
+        let index = (level.clamp(1, 5) - 1) as usize;
+        self.fee_schedule[index]
+    }
+}
+
+/// 可轮换的平台角色
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Pauser,     // 暂停权限
+    FeeManager, // 手续费管理
+}
+
+/// 平台可调参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigParams {
+    pub dispute_fee: u64,             // 争议手续费 (基点)
+    pub dispute_sol_bond: u64,        // 以 SOL 缴纳的争议保证金 (lamports)
+    pub order_expiry: i64,            // 挂单有效期 (秒)
+    pub min_payment_window: i64,      // 最短付款时限 (秒)
+    pub max_payment_window: i64,      // 最长付款时限 (秒)
+    pub auto_release_window: i64,     // 付款后自动放行时限 (秒)
+    pub min_order_amount: u64,        // 最小挂单数量
+    pub max_order_amount: u64,        // 最大挂单数量
+    pub dispute_response_window: i64, // 被诉方举证时限 (秒)
+    pub dispute_penalty: u32,         // 争议败诉扣除信誉分
+    pub appeal_window: i64,           // 裁决后上诉期 (秒, 0 为不可上诉)
+    pub appeal_fee: u64,              // 上诉保证金 (基点)
+    pub appeal_commit_window: i64,    // 合议庭提交投票承诺时限 (秒)
+    pub appeal_reveal_window: i64,    // 合议庭揭示投票时限 (秒)
+    pub vote_bond_fee: u64,           // 合议庭投票保证金 (基点)
+    pub reputation_base_gain: u32,    // 每个成交量级的基础信誉分
+    pub reputation_volume_unit: u64,  // 成交量级单位 (按 log2 计)
+    pub reputation_daily_cap: u32,    // 每日信誉增长上限
+    pub reputation_half_life: i64,    // 信誉衰减半衰期 (秒, 0 为不衰减)
+    pub panel_threshold: u64,         // 首审即由合议庭裁决的成交数量门槛
+    pub rating_window: i64,           // 成交完成后的评价期 (秒)
 }
 
 /// 托管账户
 #[account]
 pub struct EscrowAccount {
     pub order_id: u64,                       // 关联订单
+    pub trade_id: u64,                       // 关联成交
     pub seller: Pubkey,                      // 卖家
     pub buyer: Pubkey,                       // 买家
     pub token_mint: Pubkey,                  // 代币类型
@@ -32,6 +193,7 @@ pub struct EscrowAccount {
 
 impl EscrowAccount {
     pub const SIZE: usize = 8 +     // order_id
+        8 +                         // trade_id
         32 +                        // seller
         32 +                        // buyer
         32 +                        // token_mint
@@ -50,3 +212,34 @@ pub enum EscrowStatus {
     Refunded, // 已退款
     Disputed, // 争议中
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PlatformConfig {
+        PlatformConfig {
+            reputation_base_gain: 5,
+            reputation_volume_unit: 1_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gain_scales_with_log_of_trade_size() {
+        let config = config();
+        assert_eq!(config.trade_reputation_gain(0, 0), 0);
+        assert_eq!(config.trade_reputation_gain(999, 0), 0);
+        assert_eq!(config.trade_reputation_gain(1_000, 0), 5);
+        assert_eq!(config.trade_reputation_gain(3_000, 0), 10);
+        assert_eq!(config.trade_reputation_gain(u64::MAX, 0), 5 * 54);
+    }
+
+    #[test]
+    fn gain_shrinks_for_repeat_pairs() {
+        let config = config();
+        assert_eq!(config.trade_reputation_gain(3_000, 1), 5);
+        assert_eq!(config.trade_reputation_gain(3_000, 3), 2);
+        assert_eq!(config.trade_reputation_gain(3_000, u32::MAX), 0);
+    }
+}
@@ -1,76 +1,248 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, CloseAccount, Transfer};
 
 pub mod dispute;
 pub mod escrow;
 pub mod order;
 pub mod reputation;
+pub mod treasury;
 pub mod utils;
 
 pub use dispute::*;
 pub use escrow::*;
 pub use order::*;
 pub use reputation::*;
+pub use treasury::*;
 pub use utils::*;
 
 declare_id!("6YYc6JKwDftka6eWAALmAwNoprnz8ZCh3AZFbSdn4LVu");
 
-pub const PLATFORM_FEE: u64 = 50;
-pub const DISPUTE_FEE: u64 = 100;
 pub const ORDER_EXPIRY: i64 = 86400;
+pub const MIN_PAYMENT_WINDOW: i64 = 900;
+pub const MAX_PAYMENT_WINDOW: i64 = 3600;
+pub const MIN_ORDER_AMOUNT: u64 = 1;
+pub const MAX_ORDER_AMOUNT: u64 = u64::MAX;
+pub const MAX_PLATFORM_FEE: u64 = 500;
+pub const MAX_DISPUTE_FEE: u64 = 1000;
+pub const DISPUTE_RESPONSE_WINDOW: i64 = 172800;
+pub const DISPUTE_PENALTY: u32 = 20;
+pub const APPEAL_WINDOW: i64 = 86400;
+pub const APPEAL_FEE: u64 = 200;
+pub const APPEAL_COMMIT_WINDOW: i64 = 172800;
+pub const APPEAL_REVEAL_WINDOW: i64 = 86400;
+pub const VOTE_BOND_FEE: u64 = 50;
+pub const REPUTATION_BASE_GAIN: u32 = 5;
+pub const REPUTATION_VOLUME_UNIT: u64 = 1_000_000_000;
+pub const REPUTATION_DAILY_CAP: u32 = 50;
+pub const REPUTATION_HALF_LIFE: i64 = 15552000;
+pub const PANEL_THRESHOLD: u64 = 1_000_000_000_000;
+pub const RATING_WINDOW: i64 = 604800;
+pub const DISPUTE_SOL_BOND: u64 = 100_000_000;
 
 #[program]
 pub mod tpot_p2p {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, platform_fee: u64, dispute_fee: u64) -> Result<()> {
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        fee_schedule: [u64; 5],
+        dispute_fee: u64,
+        auto_release_window: i64,
+    ) -> Result<()> {
+        require!(
+            fee_schedule.iter().all(|rate| *rate <= MAX_PLATFORM_FEE),
+            P2PError::InvalidFeeRate
+        );
+        require!(dispute_fee <= MAX_DISPUTE_FEE, P2PError::InvalidFeeRate);
+        require!(auto_release_window > 0, P2PError::InvalidAutoReleaseWindow);
+
         let config = &mut ctx.accounts.platform_config;
         config.authority = ctx.accounts.authority.key();
-        config.platform_fee = platform_fee;
+        config.pending_authority = None;
+        config.pauser = ctx.accounts.authority.key();
+        config.fee_manager = ctx.accounts.authority.key();
+        config.arbitrators = Vec::new();
+        config.arbitrator_cursor = 0;
         config.dispute_fee = dispute_fee;
+        config.dispute_sol_bond = DISPUTE_SOL_BOND;
         config.paused = false;
         config.bump = ctx.bumps.platform_config;
+        config.auto_release_window = auto_release_window;
+        config.next_order_id = 0;
+        config.fee_schedule = fee_schedule;
+        config.order_expiry = ORDER_EXPIRY;
+        config.min_payment_window = MIN_PAYMENT_WINDOW;
+        config.max_payment_window = MAX_PAYMENT_WINDOW;
+        config.min_order_amount = MIN_ORDER_AMOUNT;
+        config.max_order_amount = MAX_ORDER_AMOUNT;
+        config.dispute_response_window = DISPUTE_RESPONSE_WINDOW;
+        config.dispute_penalty = DISPUTE_PENALTY;
+        config.appeal_window = APPEAL_WINDOW;
+        config.appeal_fee = APPEAL_FEE;
+        config.appeal_commit_window = APPEAL_COMMIT_WINDOW;
+        config.appeal_reveal_window = APPEAL_REVEAL_WINDOW;
+        config.vote_bond_fee = VOTE_BOND_FEE;
+        config.reputation_base_gain = REPUTATION_BASE_GAIN;
+        config.reputation_volume_unit = REPUTATION_VOLUME_UNIT;
+        config.reputation_daily_cap = REPUTATION_DAILY_CAP;
+        config.reputation_half_life = REPUTATION_HALF_LIFE;
+        config.panel_threshold = PANEL_THRESHOLD;
+        config.rating_window = RATING_WINDOW;
 
         emit!(PlatformInitialized {
             authority: ctx.accounts.authority.key(),
-            platform_fee,
+            fee_schedule,
+        });
+        Ok(())
+    }
+
+    /// 创建用户资料并登记用户名
+    pub fn create_profile(
+        ctx: Context<CreateProfile>,
+        username: String,
+        avatar: Option<String>,
+    ) -> Result<()> {
+        require!(is_valid_username(&username), P2PError::InvalidUsername);
+        if let Some(avatar) = &avatar {
+            require!(avatar.len() <= MAX_AVATAR_LEN, P2PError::AvatarTooLong);
+        }
+
+        let clock = Clock::get()?;
+        let profile = &mut ctx.accounts.profile;
+        require!(profile.username.is_none(), P2PError::UsernameAlreadySet);
+        if profile.wallet == Pubkey::default() {
+            profile.wallet = ctx.accounts.user.key();
+            profile.created_at = clock.unix_timestamp;
+            profile.bump = ctx.bumps.profile;
+        }
+
+        let history = &mut ctx.accounts.reputation_history;
+        if history.user == Pubkey::default() {
+            history.user = ctx.accounts.user.key();
+            history.bump = ctx.bumps.reputation_history;
+        }
+        profile.apply_decay(
+            history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
+        );
+        profile.username = Some(username.clone());
+        profile.avatar = avatar;
+
+        let record = &mut ctx.accounts.username_record;
+        record.owner = ctx.accounts.user.key();
+        record.bump = ctx.bumps.username_record;
+
+        emit!(ProfileCreated {
+            wallet: ctx.accounts.user.key(),
+            username,
+        });
+        Ok(())
+    }
+
+    pub fn update_profile(ctx: Context<UpdateProfile>, avatar: Option<String>) -> Result<()> {
+        if let Some(avatar) = &avatar {
+            require!(avatar.len() <= MAX_AVATAR_LEN, P2PError::AvatarTooLong);
+        }
+
+        let profile = &mut ctx.accounts.profile;
+        profile.apply_decay(
+            &mut ctx.accounts.reputation_history,
+            ctx.accounts.platform_config.reputation_half_life,
+            Clock::get()?.unix_timestamp,
+        );
+        profile.avatar = avatar.clone();
+
+        emit!(ProfileUpdated {
+            wallet: ctx.accounts.user.key(),
+            avatar,
+        });
+        Ok(())
+    }
+
+    pub fn change_username(ctx: Context<ChangeUsername>, username: String) -> Result<()> {
+        require!(is_valid_username(&username), P2PError::InvalidUsername);
+
+        let record = &mut ctx.accounts.new_username_record;
+        record.owner = ctx.accounts.user.key();
+        record.bump = ctx.bumps.new_username_record;
+
+        let profile = &mut ctx.accounts.profile;
+        let old_username = profile
+            .username
+            .replace(username.clone())
+            .unwrap_or_default();
+        profile.apply_decay(
+            &mut ctx.accounts.reputation_history,
+            ctx.accounts.platform_config.reputation_half_life,
+            Clock::get()?.unix_timestamp,
+        );
+
+        emit!(UsernameChanged {
+            wallet: ctx.accounts.user.key(),
+            old_username,
+            new_username: username,
         });
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_buy_order(
         ctx: Context<CreateBuyOrder>,
-        order_id: u64,
         amount: u64,
         price: u64,
         payment_method: String,
         min_limit: u64,
         max_limit: u64,
+        payment_window: i64,
+        requirements: OrderRequirements,
     ) -> Result<()> {
         require!(
             !ctx.accounts.platform_config.paused,
             P2PError::PlatformPaused
         );
-        require!(amount > 0, P2PError::InvalidAmount);
+        let config = &ctx.accounts.platform_config;
+        require!(
+            amount > 0 && amount >= config.min_order_amount && amount <= config.max_order_amount,
+            P2PError::InvalidAmount
+        );
         require!(price > 0, P2PError::InvalidPrice);
         require!(min_limit <= max_limit, P2PError::InvalidLimit);
         require!(payment_method.len() <= 16, P2PError::PaymentMethodTooLong);
+        require!(
+            payment_window >= config.min_payment_window
+                && payment_window <= config.max_payment_window,
+            P2PError::InvalidPaymentWindow
+        );
+        require!(
+            requirements.min_level <= 5,
+            P2PError::InvalidOrderRequirements
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        let order_id = config.next_order_id;
+        let order_expiry = config.order_expiry;
+        config.next_order_id += 1;
 
         let clock = Clock::get()?;
         let order = &mut ctx.accounts.order;
         order.order_id = order_id;
         order.maker = ctx.accounts.maker.key();
-        order.taker = None;
         order.order_type = OrderType::Buy;
         order.token_mint = ctx.accounts.token_mint.key();
         order.amount = amount;
+        order.remaining_amount = amount;
         order.price = price;
         order.payment_method = payment_method.clone();
         order.status = OrderStatus::Pending;
         order.created_at = clock.unix_timestamp;
-        order.expires_at = clock.unix_timestamp + ORDER_EXPIRY;
+        order.expires_at = clock.unix_timestamp + order_expiry;
         order.min_limit = min_limit;
         order.max_limit = max_limit;
+        order.payment_window = payment_window;
+        order.requirements = requirements;
+        order.trade_count = 0;
         order.bump = ctx.bumps.order;
 
         let profile = &mut ctx.accounts.maker_profile;
@@ -78,8 +250,18 @@ pub mod tpot_p2p {
             profile.wallet = ctx.accounts.maker.key();
             profile.bump = ctx.bumps.maker_profile;
         }
+
+        let history = &mut ctx.accounts.maker_history;
+        if history.user == Pubkey::default() {
+            history.user = ctx.accounts.maker.key();
+            history.bump = ctx.bumps.maker_history;
+        }
+        profile.apply_decay(
+            history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
+        );
         profile.total_orders += 1;
-        profile.updated_at = clock.unix_timestamp;
 
         emit!(OrderCreated {
             order_id,
@@ -91,90 +273,102 @@ pub mod tpot_p2p {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_sell_order(
         ctx: Context<CreateSellOrder>,
-        order_id: u64,
         amount: u64,
         price: u64,
         payment_method: String,
         min_limit: u64,
         max_limit: u64,
+        payment_window: i64,
+        requirements: OrderRequirements,
     ) -> Result<()> {
-        require!(amount > 0, P2PError::InvalidAmount);
+        require!(
+            !ctx.accounts.platform_config.paused,
+            P2PError::PlatformPaused
+        );
+        let config = &ctx.accounts.platform_config;
+        require!(
+            amount > 0 && amount >= config.min_order_amount && amount <= config.max_order_amount,
+            P2PError::InvalidAmount
+        );
         require!(price > 0, P2PError::InvalidPrice);
         require!(min_limit <= max_limit, P2PError::InvalidLimit);
         require!(payment_method.len() <= 16, P2PError::PaymentMethodTooLong);
+        require!(
+            payment_window >= config.min_payment_window
+                && payment_window <= config.max_payment_window,
+            P2PError::InvalidPaymentWindow
+        );
+        require!(
+            requirements.min_level <= 5,
+            P2PError::InvalidOrderRequirements
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        let order_id = config.next_order_id;
+        let order_expiry = config.order_expiry;
+        config.next_order_id += 1;
 
         let clock = Clock::get()?;
         let order = &mut ctx.accounts.order;
         order.order_id = order_id;
         order.maker = ctx.accounts.maker.key();
-        order.taker = None;
         order.order_type = OrderType::Sell;
         order.token_mint = ctx.accounts.token_mint.key();
         order.amount = amount;
+        order.remaining_amount = amount;
         order.price = price;
         order.payment_method = payment_method.clone();
         order.status = OrderStatus::Pending;
         order.created_at = clock.unix_timestamp;
-        order.expires_at = clock.unix_timestamp + ORDER_EXPIRY;
+        order.expires_at = clock.unix_timestamp + order_expiry;
         order.min_limit = min_limit;
         order.max_limit = max_limit;
+        order.payment_window = payment_window;
+        order.requirements = requirements;
+        order.trade_count = 0;
         order.bump = ctx.bumps.order;
 
-        let escrow = &mut ctx.accounts.escrow;
-        escrow.order_id = order_id;
-        escrow.seller = ctx.accounts.maker.key();
-        escrow.buyer = Pubkey::default();
-        escrow.token_mint = ctx.accounts.token_mint.key();
-        escrow.amount = amount;
-        escrow.status = EscrowStatus::Locked;
-        escrow.created_at = clock.unix_timestamp;
-        escrow.release_signature = None;
-        escrow.bump = ctx.bumps.escrow;
-
         let profile = &mut ctx.accounts.maker_profile;
         if profile.wallet == Pubkey::default() {
             profile.wallet = ctx.accounts.maker.key();
             profile.bump = ctx.bumps.maker_profile;
         }
-        profile.total_orders += 1;
-        profile.updated_at = clock.unix_timestamp;
-
-        emit!(OrderCreated {
-            order_id,
-            maker: ctx.accounts.maker.key(),
-            order_type: OrderType::Sell,
-            amount,
-            price,
-        });
-        Ok(())
-    }
 
-    pub fn deposit_escrow(ctx: Context<DepositEscrow>, order_id: u64) -> Result<()> {
-        let order = &ctx.accounts.order;
-        require!(
-            order.order_type == OrderType::Sell,
-            P2PError::InvalidOrderType
-        );
-        require!(
-            order.maker == ctx.accounts.maker.key(),
-            P2PError::NotAuthorized
+        let history = &mut ctx.accounts.maker_history;
+        if history.user == Pubkey::default() {
+            history.user = ctx.accounts.maker.key();
+            history.bump = ctx.bumps.maker_history;
+        }
+        profile.apply_decay(
+            history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
         );
+        profile.total_orders += 1;
 
         let cpi_accounts = Transfer {
             from: ctx.accounts.maker_token_account.to_account_info(),
-            to: ctx.accounts.escrow_token_account.to_account_info(),
+            to: ctx.accounts.order_vault.to_account_info(),
             authority: ctx.accounts.maker.to_account_info(),
         };
         token::transfer(
             CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
-            order.amount,
+            amount,
         )?;
 
+        emit!(OrderCreated {
+            order_id,
+            maker: ctx.accounts.maker.key(),
+            order_type: OrderType::Sell,
+            amount,
+            price,
+        });
         emit!(EscrowLocked {
             order_id,
-            amount: order.amount,
+            amount,
             seller: ctx.accounts.maker.key(),
         });
         Ok(())
@@ -191,7 +385,7 @@ pub mod tpot_p2p {
             order.status == OrderStatus::Pending,
             P2PError::OrderNotPending
         );
-        require!(order.taker.is_none(), P2PError::OrderAlreadyTaken);
+        require!(order.maker != ctx.accounts.taker.key(), P2PError::SelfTrade);
 
         let clock = Clock::get()?;
         require!(
@@ -199,27 +393,101 @@ pub mod tpot_p2p {
             P2PError::OrderExpired
         );
         require!(
-            amount >= order.min_limit && amount <= order.max_limit,
+            amount > 0
+                && amount >= order.min_limit
+                && amount <= order.max_limit
+                && amount <= order.remaining_amount,
             P2PError::InvalidAmount
         );
 
-        order.taker = Some(ctx.accounts.taker.key());
-        order.amount = amount;
-        order.status = OrderStatus::Matched;
-
-        if order.order_type == OrderType::Buy {
-            let escrow = &mut ctx.accounts.escrow;
-            escrow.order_id = order_id;
-            escrow.seller = order.maker;
-            escrow.buyer = ctx.accounts.taker.key();
-            escrow.token_mint = order.token_mint;
-            escrow.amount = amount;
-            escrow.status = EscrowStatus::Locked;
-            escrow.created_at = clock.unix_timestamp;
-            escrow.bump = ctx.bumps.escrow;
+        let trade_id = order.trade_count;
+        order.trade_count += 1;
+        order.remaining_amount -= amount;
+        if !order.is_fillable() {
+            order.status = OrderStatus::Completed;
+        }
+
+        let trade = &mut ctx.accounts.trade;
+        trade.order_id = order_id;
+        trade.trade_id = trade_id;
+        trade.maker = order.maker;
+        trade.taker = ctx.accounts.taker.key();
+        trade.order_type = order.order_type;
+        trade.token_mint = order.token_mint;
+        trade.amount = amount;
+        trade.price = order.price;
+        trade.status = OrderStatus::Matched;
+        trade.created_at = clock.unix_timestamp;
+        trade.payment_deadline = clock.unix_timestamp + order.payment_window;
+        trade.paid_at = None;
+        trade.completed_at = None;
+        trade.bump = ctx.bumps.trade;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.order_id = order_id;
+        escrow.trade_id = trade_id;
+        escrow.seller = trade.seller();
+        escrow.buyer = trade.buyer();
+        escrow.token_mint = order.token_mint;
+        escrow.amount = amount;
+        escrow.status = EscrowStatus::Locked;
+        escrow.created_at = clock.unix_timestamp;
+        escrow.release_signature = None;
+        escrow.bump = ctx.bumps.escrow;
+
+        // 卖单: 从广告托管中划出本次成交的份额
+        if order.order_type == OrderType::Sell {
+            let order_vault = ctx
+                .accounts
+                .order_vault
+                .as_ref()
+                .ok_or(P2PError::EscrowNotFound)?;
+            require!(order_vault.amount >= amount, P2PError::InsufficientBalance);
+
+            let order_id_bytes = order_id.to_le_bytes();
+            let seeds = &[b"order", order_id_bytes.as_ref(), &[order.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: order_vault.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: order.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer,
+                ),
+                amount,
+            )?;
         } else {
-            let escrow = &mut ctx.accounts.escrow;
-            escrow.buyer = ctx.accounts.taker.key();
+            // 买单: 接单的卖家将本次成交数量存入托管
+            let taker_token_account = ctx
+                .accounts
+                .taker_token_account
+                .as_ref()
+                .ok_or(P2PError::MissingTokenAccount)?;
+            require!(
+                taker_token_account.amount >= amount,
+                P2PError::InsufficientBalance
+            );
+
+            let cpi_accounts = Transfer {
+                from: taker_token_account.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: ctx.accounts.taker.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+                amount,
+            )?;
+
+            emit!(EscrowLocked {
+                order_id,
+                amount,
+                seller: ctx.accounts.taker.key(),
+            });
         }
 
         let profile = &mut ctx.accounts.taker_profile;
@@ -227,12 +495,32 @@ pub mod tpot_p2p {
             profile.wallet = ctx.accounts.taker.key();
             profile.bump = ctx.bumps.taker_profile;
         }
+
+        let history = &mut ctx.accounts.taker_history;
+        if history.user == Pubkey::default() {
+            history.user = ctx.accounts.taker.key();
+            history.bump = ctx.bumps.taker_history;
+        }
+        profile.apply_decay(
+            history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
+        );
+        order.requirements.check(profile)?;
         profile.total_trades += 1;
-        profile.updated_at = clock.unix_timestamp;
+
+        let pair = &mut ctx.accounts.trade_pair;
+        if pair.traders[0] == Pubkey::default() {
+            pair.traders = sorted_pair(order.maker, ctx.accounts.taker.key());
+            pair.bump = ctx.bumps.trade_pair;
+        }
 
         emit!(OrderTaken {
             order_id,
+            trade_id,
             taker: ctx.accounts.taker.key(),
+            amount,
+            remaining_amount: order.remaining_amount,
         });
         Ok(())
     }
@@ -240,105 +528,205 @@ pub mod tpot_p2p {
     pub fn confirm_payment(
         ctx: Context<ConfirmPayment>,
         order_id: u64,
+        trade_id: u64,
         _payment_proof: String,
     ) -> Result<()> {
-        let order = &mut ctx.accounts.order;
+        let trade = &mut ctx.accounts.trade;
         require!(
-            order.status == OrderStatus::Matched,
+            trade.status == OrderStatus::Matched,
             P2PError::OrderNotMatched
         );
+        require!(
+            trade.buyer() == ctx.accounts.payer.key(),
+            P2PError::NotBuyer
+        );
 
-        let is_buyer = if order.order_type == OrderType::Buy {
-            order.maker == ctx.accounts.payer.key()
-        } else {
-            order.taker.unwrap() == ctx.accounts.payer.key()
-        };
-        require!(is_buyer, P2PError::NotBuyer);
-
-        order.status = OrderStatus::Paid;
+        trade.status = OrderStatus::Paid;
+        trade.paid_at = Some(Clock::get()?.unix_timestamp);
 
         emit!(PaymentConfirmed {
             order_id,
+            trade_id,
             payer: ctx.accounts.payer.key(),
         });
         Ok(())
     }
 
-    pub fn release_tokens(ctx: Context<ReleaseTokens>, order_id: u64) -> Result<()> {
-        let order = &mut ctx.accounts.order;
-        require!(order.status == OrderStatus::Paid, P2PError::OrderNotPaid);
-
-        let is_seller = if order.order_type == OrderType::Sell {
-            order.maker == ctx.accounts.seller.key()
+    pub fn release_tokens(ctx: Context<ReleaseTokens>, order_id: u64, trade_id: u64) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        require!(trade.status == OrderStatus::Paid, P2PError::OrderNotPaid);
+        require!(
+            trade.seller() == ctx.accounts.seller.key(),
+            P2PError::NotSeller
+        );
+        let seller_profile = if trade.seller() == trade.maker {
+            &ctx.accounts.maker_profile
         } else {
-            order.taker.unwrap() == ctx.accounts.seller.key()
+            &ctx.accounts.taker_profile
         };
-        require!(is_seller, P2PError::NotSeller);
+        require!(!seller_profile.is_banned, P2PError::UserBanned);
+
+        let (release_amount, fee_rate) = settle_trade(
+            &mut ctx.accounts.trade,
+            &mut ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.buyer_token_account,
+            &ctx.accounts.treasury,
+            &ctx.accounts.token_program,
+            &ctx.accounts.platform_config,
+            (
+                &mut ctx.accounts.maker_profile,
+                &mut ctx.accounts.maker_history,
+            ),
+            (
+                &mut ctx.accounts.taker_profile,
+                &mut ctx.accounts.taker_history,
+            ),
+            &mut ctx.accounts.trade_pair,
+            true,
+        )?;
+        let buyer = ctx.accounts.trade.buyer();
 
-        let escrow = &mut ctx.accounts.escrow;
-        let fee = calculate_fee(order.amount, ctx.accounts.platform_config.platform_fee);
-        let release_amount = order.amount.checked_sub(fee).unwrap();
+        emit!(TokensReleased {
+            order_id,
+            trade_id,
+            buyer,
+            amount: release_amount,
+            fee_rate,
+        });
+        emit!(OrderCompleted {
+            order_id,
+            trade_id,
+            buyer,
+            seller: ctx.accounts.seller.key(),
+        });
+        Ok(())
+    }
 
-        let order_id_bytes = order_id.to_le_bytes();
-        let seeds = &[b"escrow", order_id_bytes.as_ref(), &[escrow.bump]];
-        let signer = &[&seeds[..]];
+    /// 买家付款后卖家超时未放行, 任何人均可触发自动放行
+    pub fn auto_release_tokens(
+        ctx: Context<AutoReleaseTokens>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        require!(trade.status == OrderStatus::Paid, P2PError::OrderNotPaid);
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.buyer_token_account.to_account_info(),
-            authority: escrow.to_account_info(),
-        };
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-                signer,
+        let clock = Clock::get()?;
+        let paid_at = trade.paid_at.ok_or(P2PError::OrderNotPaid)?;
+        require!(
+            clock.unix_timestamp >= paid_at + ctx.accounts.platform_config.auto_release_window,
+            P2PError::AutoReleaseNotDue
+        );
+
+        let (release_amount, fee_rate) = settle_trade(
+            &mut ctx.accounts.trade,
+            &mut ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.buyer_token_account,
+            &ctx.accounts.treasury,
+            &ctx.accounts.token_program,
+            &ctx.accounts.platform_config,
+            (
+                &mut ctx.accounts.maker_profile,
+                &mut ctx.accounts.maker_history,
+            ),
+            (
+                &mut ctx.accounts.taker_profile,
+                &mut ctx.accounts.taker_history,
             ),
-            release_amount,
+            &mut ctx.accounts.trade_pair,
+            false,
         )?;
+        let buyer = ctx.accounts.trade.buyer();
+
+        emit!(TokensReleased {
+            order_id,
+            trade_id,
+            buyer,
+            amount: release_amount,
+            fee_rate,
+        });
+        emit!(OrderCompleted {
+            order_id,
+            trade_id,
+            buyer,
+            seller: ctx.accounts.trade.seller(),
+        });
+        Ok(())
+    }
+
+    /// 买家超时未付款, 任何人均可触发取消并退回卖家托管
+    pub fn cancel_unpaid_trade(
+        ctx: Context<CancelUnpaidTrade>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
+        let trade = &mut ctx.accounts.trade;
+        require!(
+            trade.status == OrderStatus::Matched,
+            P2PError::OrderNotMatched
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= trade.payment_deadline,
+            P2PError::PaymentWindowNotElapsed
+        );
 
-        if fee > 0 {
-            let fee_accounts = Transfer {
+        let escrow = &mut ctx.accounts.escrow;
+        let refund_amount = ctx.accounts.escrow_token_account.amount;
+        if refund_amount > 0 {
+            let order_id_bytes = order_id.to_le_bytes();
+            let trade_id_bytes = trade_id.to_le_bytes();
+            let seeds = &[
+                b"escrow",
+                order_id_bytes.as_ref(),
+                trade_id_bytes.as_ref(),
+                &[escrow.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
                 from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.fee_token_account.to_account_info(),
+                to: ctx.accounts.seller_token_account.to_account_info(),
                 authority: escrow.to_account_info(),
             };
             token::transfer(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    fee_accounts,
+                    cpi_accounts,
                     signer,
                 ),
-                fee,
+                refund_amount,
             )?;
         }
 
-        order.status = OrderStatus::Completed;
-        escrow.status = EscrowStatus::Released;
-
-        let buyer = if order.order_type == OrderType::Sell {
-            order.taker.unwrap()
-        } else {
-            order.maker
-        };
+        trade.status = OrderStatus::Cancelled;
+        escrow.status = EscrowStatus::Refunded;
 
-        ctx.accounts.maker_profile.completed_trades += 1;
-        ctx.accounts.maker_profile.add_reputation(10);
-        ctx.accounts.maker_profile.update_completion_rate();
-
-        ctx.accounts.taker_profile.completed_trades += 1;
-        ctx.accounts.taker_profile.add_reputation(10);
-        ctx.accounts.taker_profile.update_completion_rate();
+        let buyer_profile = &mut ctx.accounts.buyer_profile;
+        let buyer_history = &mut ctx.accounts.buyer_history;
+        buyer_profile.apply_decay(
+            buyer_history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
+        );
+        buyer_profile.cancelled_trades += 1;
+        buyer_profile.change_reputation(
+            buyer_history,
+            -5,
+            ReputationReason::TradeCancelled,
+            Some(order_id),
+            clock.unix_timestamp,
+        );
+        buyer_profile.update_completion_rate();
 
-        emit!(TokensReleased {
-            order_id,
-            buyer,
-            amount: release_amount,
-        });
-        emit!(OrderCompleted {
+        emit!(TradeCancelled {
             order_id,
-            buyer,
-            seller: ctx.accounts.seller.key(),
+            trade_id,
+            buyer: trade.buyer(),
+            refund_amount,
         });
         Ok(())
     }
@@ -346,30 +734,40 @@ pub mod tpot_p2p {
     pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u64, _reason: String) -> Result<()> {
         let order = &mut ctx.accounts.order;
 
-        let can_cancel = order.status == OrderStatus::Pending
-            || (order.status == OrderStatus::Matched && order.taker.is_none());
+        // 已吃满的广告仍可取消, 用于取回低于最小限额的剩余托管
+        let is_listed = order.status == OrderStatus::Pending;
+        let can_cancel =
+            is_listed || (order.status == OrderStatus::Completed && order.remaining_amount > 0);
         require!(can_cancel, P2PError::OrderCannotCancel);
+        require!(
+            order.maker == ctx.accounts.canceler.key(),
+            P2PError::NotAuthorized
+        );
 
-        let is_maker = order.maker == ctx.accounts.canceler.key();
-        let is_taker = order
-            .taker
-            .map(|t| t == ctx.accounts.canceler.key())
-            .unwrap_or(false);
-        require!(is_maker || is_taker, P2PError::NotAuthorized);
-
+        let refund_amount = order.remaining_amount;
+        order.remaining_amount = 0;
         order.status = OrderStatus::Cancelled;
 
-        if order.order_type == OrderType::Sell {
-            let escrow = &mut ctx.accounts.escrow;
+        if order.order_type == OrderType::Sell && refund_amount > 0 {
+            let order_vault = ctx
+                .accounts
+                .order_vault
+                .as_ref()
+                .ok_or(P2PError::EscrowNotFound)?;
+            let seller_token_account = ctx
+                .accounts
+                .seller_token_account
+                .as_ref()
+                .ok_or(P2PError::EscrowNotFound)?;
 
             let order_id_bytes = order_id.to_le_bytes();
-            let seeds = &[b"escrow", order_id_bytes.as_ref(), &[escrow.bump]];
+            let seeds = &[b"order", order_id_bytes.as_ref(), &[order.bump]];
             let signer = &[&seeds[..]];
 
             let cpi_accounts = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.seller_token_account.to_account_info(),
-                authority: escrow.to_account_info(),
+                from: order_vault.to_account_info(),
+                to: seller_token_account.to_account_info(),
+                authority: order.to_account_info(),
             };
             token::transfer(
                 CpiContext::new_with_signer(
@@ -377,15 +775,29 @@ pub mod tpot_p2p {
                     cpi_accounts,
                     signer,
                 ),
-                order.amount,
+                refund_amount,
             )?;
-
-            escrow.status = EscrowStatus::Refunded;
         }
 
-        ctx.accounts.canceler_profile.cancelled_trades += 1;
-        ctx.accounts.canceler_profile.subtract_reputation(5);
-        ctx.accounts.canceler_profile.update_completion_rate();
+        if is_listed {
+            let now = Clock::get()?.unix_timestamp;
+            let profile = &mut ctx.accounts.canceler_profile;
+            let history = &mut ctx.accounts.canceler_history;
+            profile.apply_decay(
+                history,
+                ctx.accounts.platform_config.reputation_half_life,
+                now,
+            );
+            profile.cancelled_trades += 1;
+            profile.change_reputation(
+                history,
+                -5,
+                ReputationReason::OrderCancelled,
+                Some(order_id),
+                now,
+            );
+            profile.update_completion_rate();
+        }
 
         emit!(OrderCancelled {
             order_id,
@@ -394,97 +806,1105 @@ pub mod tpot_p2p {
         Ok(())
     }
 
-    pub fn open_dispute(
-        ctx: Context<OpenDispute>,
+    /// 开启争议并按轮询从仲裁员名册中指派仲裁员,
+    /// 被跳过的 (停用或额度不足) 仲裁员账户需按顺序放入 remaining_accounts
+    pub fn open_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenDispute<'info>>,
         order_id: u64,
+        trade_id: u64,
         reason: String,
         evidence_hashes: Vec<[u8; 32]>,
+        sol_bond: bool,
     ) -> Result<()> {
         require!(
             !ctx.accounts.platform_config.paused,
             P2PError::PlatformPaused
         );
 
-        let order = &mut ctx.accounts.order;
+        let trade = &mut ctx.accounts.trade;
         require!(
-            order.status == OrderStatus::Matched || order.status == OrderStatus::Paid,
+            trade.status == OrderStatus::Matched || trade.status == OrderStatus::Paid,
             P2PError::OrderNotMatched
         );
 
-        let is_participant = order.maker == ctx.accounts.disputer.key()
-            || order
-                .taker
-                .map(|t| t == ctx.accounts.disputer.key())
-                .unwrap_or(false);
+        let is_participant = trade.maker == ctx.accounts.disputer.key()
+            || trade.taker == ctx.accounts.disputer.key();
         require!(is_participant, P2PError::NotParticipant);
+        let disputer_profile = if trade.maker == ctx.accounts.disputer.key() {
+            &ctx.accounts.maker_profile
+        } else {
+            &ctx.accounts.taker_profile
+        };
+        require!(!disputer_profile.is_banned, P2PError::UserBanned);
+        require!(
+            evidence_hashes.len() <= MAX_EVIDENCE_PER_PARTY,
+            P2PError::EvidenceLimitReached
+        );
+
+        // 发起方缴纳保证金, 胜诉退还, 败诉没收; 代币按争议手续费率缴纳,
+        // 未持有该代币的一方 (如卖单中的买家) 可改为缴纳固定数额的 SOL, 存放于争议账户
+        let bond_amount = if sol_bond {
+            let bond_amount = ctx.accounts.platform_config.dispute_sol_bond;
+            if bond_amount > 0 {
+                let cpi_accounts = system_program::Transfer {
+                    from: ctx.accounts.disputer.to_account_info(),
+                    to: ctx.accounts.dispute.to_account_info(),
+                };
+                system_program::transfer(
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts),
+                    bond_amount,
+                )?;
+            }
+            bond_amount
+        } else {
+            let bond_amount = calculate_fee(trade.amount, ctx.accounts.platform_config.dispute_fee);
+            if bond_amount > 0 {
+                let disputer_token_account = ctx
+                    .accounts
+                    .disputer_token_account
+                    .as_ref()
+                    .ok_or(P2PError::MissingTokenAccount)?;
+                require!(
+                    disputer_token_account.amount >= bond_amount,
+                    P2PError::InsufficientBalance
+                );
+                let cpi_accounts = Transfer {
+                    from: disputer_token_account.to_account_info(),
+                    to: ctx.accounts.bond_vault.to_account_info(),
+                    authority: ctx.accounts.disputer.to_account_info(),
+                };
+                token::transfer(
+                    CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+                    bond_amount,
+                )?;
+            }
+            bond_amount
+        };
+
+        let config = &mut ctx.accounts.platform_config;
+        let registry_len = config.arbitrators.len();
+        require!(registry_len > 0, P2PError::NoArbitratorAvailable);
+        require!(
+            ctx.remaining_accounts.len() < registry_len,
+            P2PError::NoArbitratorAvailable
+        );
+
+        let mut index = config.arbitrator_cursor as usize % registry_len;
+        for info in ctx.remaining_accounts.iter() {
+            let skipped = Account::<Arbitrator>::try_from(info)?;
+            require!(
+                skipped.wallet == config.arbitrators[index],
+                P2PError::ArbitratorNotFound
+            );
+            require!(
+                !skipped.is_eligible(trade.amount),
+                P2PError::ArbitratorNotSkippable
+            );
+            index = (index + 1) % registry_len;
+        }
+
+        let arbitrator = &mut ctx.accounts.arbitrator;
+        require!(
+            arbitrator.wallet == config.arbitrators[index],
+            P2PError::ArbitratorNotFound
+        );
+        require!(
+            arbitrator.is_eligible(trade.amount),
+            P2PError::NoArbitratorAvailable
+        );
+        arbitrator.active_cases += 1;
+        config.arbitrator_cursor = ((index + 1) % registry_len) as u32;
 
         let clock = Clock::get()?;
         let dispute = &mut ctx.accounts.dispute;
         dispute.dispute_id = true;
         dispute.order_id = order_id;
+        dispute.trade_id = trade_id;
         dispute.plaintiff = ctx.accounts.disputer.key();
-        dispute.defendant = if order.maker == ctx.accounts.disputer.key() {
-            order.taker.unwrap()
+        dispute.defendant = if trade.maker == ctx.accounts.disputer.key() {
+            trade.taker
         } else {
-            order.maker
+            trade.maker
         };
-        dispute.arbitrator = ctx.accounts.platform_config.authority;
+        dispute.arbitrator = arbitrator.wallet;
         dispute.reason = reason.clone();
-        dispute.evidence_hashes = evidence_hashes;
+        dispute.evidence = evidence_hashes
+            .into_iter()
+            .map(|hash| Evidence {
+                submitter: ctx.accounts.disputer.key(),
+                label: String::new(),
+                hash,
+                submitted_at: clock.unix_timestamp,
+            })
+            .collect();
         dispute.status = DisputeStatus::Opened;
         dispute.ruling = None;
         dispute.created_at = clock.unix_timestamp;
+        dispute.response_deadline = clock.unix_timestamp + config.dispute_response_window;
         dispute.resolved_at = None;
+        dispute.bond_amount = bond_amount;
+        dispute.bond_in_sol = sol_bond;
+        dispute.bond_status = BondStatus::Held;
+        dispute.appealed = false;
+        dispute.panel_review = trade.amount >= config.panel_threshold;
         dispute.bump = ctx.bumps.dispute;
 
-        order.status = OrderStatus::Disputed;
+        trade.status = OrderStatus::Disputed;
+
+        let half_life = ctx.accounts.platform_config.reputation_half_life;
+        for (profile, history) in [
+            (
+                &mut ctx.accounts.maker_profile,
+                &mut ctx.accounts.maker_history,
+            ),
+            (
+                &mut ctx.accounts.taker_profile,
+                &mut ctx.accounts.taker_history,
+            ),
+        ] {
+            profile.apply_decay(history, half_life, clock.unix_timestamp);
+            profile.disputed_trades += 1;
+        }
 
         emit!(DisputeOpened {
             order_id,
+            trade_id,
             plaintiff: ctx.accounts.disputer.key(),
+            arbitrator: arbitrator.wallet,
             reason,
+            bond_amount,
+            bond_in_sol: sol_bond,
         });
         Ok(())
     }
 
-    pub fn resolve_dispute(
-        ctx: Context<ResolveDispute>,
+    /// 争议双方追加证据, 被诉方首次举证后进入举证阶段
+    pub fn submit_evidence(
+        ctx: Context<SubmitEvidence>,
         order_id: u64,
+        trade_id: u64,
+        label: String,
+        hash: [u8; 32],
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        let submitter = ctx.accounts.submitter.key();
+        require!(
+            submitter == dispute.plaintiff || submitter == dispute.defendant,
+            P2PError::NotParticipant
+        );
+        require!(
+            dispute.status == DisputeStatus::Opened
+                || dispute.status == DisputeStatus::EvidencePhase,
+            P2PError::EvidencePhaseClosed
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp < dispute.response_deadline,
+            P2PError::EvidencePhaseClosed
+        );
+        require!(
+            label.len() <= MAX_EVIDENCE_LABEL_LEN,
+            P2PError::EvidenceLabelTooLong
+        );
+        require!(
+            dispute.evidence_count(&submitter) < MAX_EVIDENCE_PER_PARTY,
+            P2PError::EvidenceLimitReached
+        );
+
+        dispute.evidence.push(Evidence {
+            submitter,
+            label: label.clone(),
+            hash,
+            submitted_at: clock.unix_timestamp,
+        });
+        if submitter == dispute.defendant {
+            dispute.status = DisputeStatus::EvidencePhase;
+        }
+
+        emit!(EvidenceSubmitted {
+            order_id,
+            trade_id,
+            submitter,
+            label,
+            hash,
+        });
+        Ok(())
+    }
+
+    /// 结束举证进入仲裁: 举证时限届满后任何人可调用,
+    /// 被诉方已应诉时指派的仲裁员可提前开始
+    pub fn start_arbitration(
+        ctx: Context<StartArbitration>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Opened
+                || dispute.status == DisputeStatus::EvidencePhase,
+            P2PError::DisputeNotOpen
+        );
+
+        let clock = Clock::get()?;
+        let early_start = dispute.status == DisputeStatus::EvidencePhase
+            && ctx.accounts.caller.key() == dispute.arbitrator;
+        require!(
+            early_start || clock.unix_timestamp >= dispute.response_deadline,
+            P2PError::ResponseWindowNotElapsed
+        );
+
+        dispute.status = DisputeStatus::Arbitrating;
+
+        emit!(ArbitrationStarted {
+            order_id,
+            trade_id,
+            arbitrator: dispute.arbitrator,
+        });
+        Ok(())
+    }
+
+    /// 仲裁员作出裁决, 资金在上诉期结束后由 finalize_dispute 划转
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        order_id: u64,
+        trade_id: u64,
         ruling: Ruling,
     ) -> Result<()> {
         require!(
-            ctx.accounts.arbitrator.key() == ctx.accounts.platform_config.authority,
+            ctx.accounts.trade.status == OrderStatus::Disputed,
+            P2PError::DisputeNotOpen
+        );
+
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Arbitrating,
+            P2PError::DisputeNotArbitrating
+        );
+        require!(
+            dispute.arbitrator == ctx.accounts.arbitrator.key(),
             P2PError::NotArbitrator
         );
+        require!(!dispute.panel_review, P2PError::PanelReviewRequired);
+        require!(ruling.is_valid(), P2PError::InvalidRuling);
 
-        let order = &mut ctx.accounts.order;
+        let clock = Clock::get()?;
+        dispute.status = DisputeStatus::Ruled;
+        dispute.ruling = Some(ruling);
+        dispute.appeal_deadline = clock.unix_timestamp + ctx.accounts.platform_config.appeal_window;
+
+        let arbitrator_account = &mut ctx.accounts.arbitrator_account;
+        arbitrator_account.active_cases = arbitrator_account.active_cases.saturating_sub(1);
+        arbitrator_account.resolved_cases += 1;
+
+        emit!(DisputeRuled {
+            order_id,
+            trade_id,
+            arbitrator: ctx.accounts.arbitrator.key(),
+            ruling,
+            appeal_deadline: dispute.appeal_deadline,
+        });
+        Ok(())
+    }
+
+    /// 高额争议进入仲裁阶段后, 由争议任一方召集合议庭首审, 取代独任仲裁员;
+    /// 沿用上诉的提交-揭示投票流程, 合议庭成员账户需按顺序放入 remaining_accounts
+    pub fn convene_panel<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ConvenePanel<'info>>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        let convener = ctx.accounts.convener.key();
         require!(
-            order.status == OrderStatus::Disputed,
-            P2PError::DisputeNotOpen
+            convener == dispute.plaintiff || convener == dispute.defendant,
+            P2PError::NotParticipant
         );
+        require!(dispute.panel_review, P2PError::PanelReviewNotRequired);
+        require!(
+            dispute.status == DisputeStatus::Arbitrating,
+            P2PError::DisputeNotArbitrating
+        );
+
+        // 原指派仲裁员不入选, 其账户已在本指令中单独结算案件数
+        let trade = &ctx.accounts.trade;
+        let panel = select_panel(
+            &mut ctx.accounts.platform_config,
+            ctx.remaining_accounts,
+            trade.amount,
+            &dispute.arbitrator,
+        )?;
+
+        // 原指派仲裁员不再独任裁决, 释放其案件
+        let arbitrator = &mut ctx.accounts.arbitrator;
+        arbitrator.active_cases = arbitrator.active_cases.saturating_sub(1);
 
+        let clock = Clock::get()?;
+        let config = &ctx.accounts.platform_config;
+        let appeal = &mut ctx.accounts.appeal;
+        appeal.order_id = order_id;
+        appeal.trade_id = trade_id;
+        appeal.appellant = convener;
+        // 首审合议庭未形成多数意见时按五五分成
+        appeal.original_ruling = Ruling::Split;
+        appeal.bond_amount = 0;
+        appeal.vote_bond = calculate_fee(trade.amount, config.vote_bond_fee);
+        appeal.panel = panel.clone();
+        appeal.votes = Vec::new();
+        appeal.status = AppealStatus::Voting;
+        appeal.final_ruling = None;
+        appeal.created_at = clock.unix_timestamp;
+        appeal.commit_deadline = clock.unix_timestamp + config.appeal_commit_window;
+        appeal.reveal_deadline = appeal.commit_deadline + config.appeal_reveal_window;
+        appeal.bump = ctx.bumps.appeal;
+
+        dispute.status = DisputeStatus::Appealed;
+        dispute.appealed = true;
+
+        emit!(PanelConvened {
+            order_id,
+            trade_id,
+            convener,
+            panel,
+        });
+        Ok(())
+    }
+
+    /// 上诉期内争议任一方缴纳上诉保证金提起上诉, 由名册中轮询选出的合议庭审理,
+    /// 合议庭成员 (及被跳过的仲裁员) 账户需按顺序放入 remaining_accounts
+    pub fn appeal_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AppealDispute<'info>>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
         let dispute = &mut ctx.accounts.dispute;
+        let appellant = ctx.accounts.appellant.key();
+        require!(
+            appellant == dispute.plaintiff || appellant == dispute.defendant,
+            P2PError::NotParticipant
+        );
+        require!(
+            dispute.status == DisputeStatus::Ruled,
+            P2PError::DisputeNotRuled
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp < dispute.appeal_deadline,
+            P2PError::AppealWindowClosed
+        );
+
+        let trade = &ctx.accounts.trade;
+        let panel = select_panel(
+            &mut ctx.accounts.platform_config,
+            ctx.remaining_accounts,
+            trade.amount,
+            &dispute.arbitrator,
+        )?;
+
+        let config = &ctx.accounts.platform_config;
+        let bond_amount = calculate_fee(trade.amount, config.appeal_fee);
+        if bond_amount > 0 {
+            require!(
+                ctx.accounts.appellant_token_account.amount >= bond_amount,
+                P2PError::InsufficientBalance
+            );
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.appellant_token_account.to_account_info(),
+                to: ctx.accounts.appeal_bond_vault.to_account_info(),
+                authority: ctx.accounts.appellant.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+                bond_amount,
+            )?;
+        }
+
+        let appeal = &mut ctx.accounts.appeal;
+        appeal.order_id = order_id;
+        appeal.trade_id = trade_id;
+        appeal.appellant = appellant;
+        appeal.original_ruling = dispute.ruling.ok_or(P2PError::DisputeNotRuled)?;
+        appeal.bond_amount = bond_amount;
+        appeal.vote_bond = calculate_fee(trade.amount, config.vote_bond_fee);
+        appeal.panel = panel.clone();
+        appeal.votes = Vec::new();
+        appeal.status = AppealStatus::Voting;
+        appeal.final_ruling = None;
+        appeal.created_at = clock.unix_timestamp;
+        appeal.commit_deadline = clock.unix_timestamp + config.appeal_commit_window;
+        appeal.reveal_deadline = appeal.commit_deadline + config.appeal_reveal_window;
+        appeal.bump = ctx.bumps.appeal;
+
+        dispute.status = DisputeStatus::Appealed;
+        dispute.appealed = true;
+
+        emit!(DisputeAppealed {
+            order_id,
+            trade_id,
+            appellant,
+            panel,
+            bond_amount,
+        });
+        Ok(())
+    }
+
+    /// 合议庭成员提交投票承诺 (见 Ruling::commitment), 同时缴纳投票保证金
+    pub fn commit_vote(
+        ctx: Context<CastVote>,
+        order_id: u64,
+        trade_id: u64,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.dispute.status == DisputeStatus::Appealed,
+            P2PError::AppealNotOpen
+        );
+
+        let appeal = &mut ctx.accounts.appeal;
+        let panelist = ctx.accounts.panelist.key();
+        require!(appeal.is_panelist(&panelist), P2PError::NotArbitrator);
+        // 入选后被移出名册或停用的仲裁员不得再投票
         require!(
-            dispute.status == DisputeStatus::Opened,
+            ctx.accounts.platform_config.is_arbitrator(&panelist)
+                && ctx.accounts.arbitrator.status == ArbitratorStatus::Active,
+            P2PError::NotArbitrator
+        );
+        require!(!appeal.has_committed(&panelist), P2PError::AlreadyVoted);
+        require!(
+            !appeal.has_commitment(&commitment),
+            P2PError::DuplicateCommitment
+        );
+        require!(
+            appeal.status == AppealStatus::Voting
+                && Clock::get()?.unix_timestamp < appeal.commit_deadline,
+            P2PError::CommitPhaseClosed
+        );
+
+        if appeal.vote_bond > 0 {
+            require!(
+                ctx.accounts.panelist_token_account.amount >= appeal.vote_bond,
+                P2PError::InsufficientBalance
+            );
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.panelist_token_account.to_account_info(),
+                to: ctx.accounts.vote_bond_vault.to_account_info(),
+                authority: ctx.accounts.panelist.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+                appeal.vote_bond,
+            )?;
+        }
+
+        appeal.votes.push(PanelVote {
+            arbitrator: panelist,
+            commitment,
+            ruling: None,
+        });
+
+        emit!(VoteCommitted {
+            order_id,
+            trade_id,
+            arbitrator: panelist,
+            commitment,
+        });
+        Ok(())
+    }
+
+    /// 合议庭成员揭示投票, 校验承诺后退还投票保证金
+    pub fn reveal_vote(
+        ctx: Context<CastVote>,
+        order_id: u64,
+        trade_id: u64,
+        ruling: Ruling,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.dispute.status == DisputeStatus::Appealed,
+            P2PError::AppealNotOpen
+        );
+
+        let appeal = &mut ctx.accounts.appeal;
+        let panelist = ctx.accounts.panelist.key();
+        require!(
+            appeal.status == AppealStatus::Voting
+                && appeal.reveal_open(Clock::get()?.unix_timestamp),
+            P2PError::RevealPhaseClosed
+        );
+        require!(ruling.is_valid(), P2PError::InvalidRuling);
+
+        let vote_bond = appeal.vote_bond;
+        let vote = appeal
+            .votes
+            .iter_mut()
+            .find(|v| v.arbitrator == panelist)
+            .ok_or(P2PError::VoteNotCommitted)?;
+        require!(vote.ruling.is_none(), P2PError::AlreadyVoted);
+        require!(
+            constant_time_eq::constant_time_eq_32(
+                &ruling.commitment(&panelist, order_id, trade_id, &salt)?,
+                &vote.commitment
+            ),
+            P2PError::CommitmentMismatch
+        );
+        vote.ruling = Some(ruling);
+
+        if vote_bond > 0 {
+            let order_id_bytes = order_id.to_le_bytes();
+            let trade_id_bytes = trade_id.to_le_bytes();
+            let dispute_seeds = &[
+                b"dispute",
+                order_id_bytes.as_ref(),
+                trade_id_bytes.as_ref(),
+                &[ctx.accounts.dispute.bump],
+            ];
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.vote_bond_vault.to_account_info(),
+                to: ctx.accounts.panelist_token_account.to_account_info(),
+                authority: ctx.accounts.dispute.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    &[&dispute_seeds[..]],
+                ),
+                vote_bond,
+            )?;
+        }
+
+        emit!(VoteRevealed {
+            order_id,
+            trade_id,
+            arbitrator: panelist,
+            ruling,
+        });
+        Ok(())
+    }
+
+    /// 结算争议: 上诉期满未上诉, 或上诉已形成多数意见/投票期满后, 任何人可调用;
+    /// 经合议的争议需将合议庭成员账户按顺序放入 remaining_accounts
+    pub fn finalize_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeDispute<'info>>,
+        order_id: u64,
+        trade_id: u64,
+    ) -> Result<()> {
+        let trade = &mut ctx.accounts.trade;
+        require!(
+            trade.status == OrderStatus::Disputed,
             P2PError::DisputeNotOpen
         );
 
         let clock = Clock::get()?;
+        let dispute = &mut ctx.accounts.dispute;
+        let order_id_bytes = order_id.to_le_bytes();
+        let trade_id_bytes = trade_id.to_le_bytes();
+        let dispute_seeds = &[
+            b"dispute",
+            order_id_bytes.as_ref(),
+            trade_id_bytes.as_ref(),
+            &[dispute.bump],
+        ];
+
+        let ruling = match dispute.status {
+            DisputeStatus::Ruled => {
+                require!(
+                    clock.unix_timestamp >= dispute.appeal_deadline,
+                    P2PError::AppealWindowOpen
+                );
+                dispute.ruling.ok_or(P2PError::DisputeNotRuled)?
+            }
+            DisputeStatus::Appealed => {
+                let appeal = ctx
+                    .accounts
+                    .appeal
+                    .as_mut()
+                    .ok_or(P2PError::AppealNotFound)?;
+                let appeal_bond_vault = ctx
+                    .accounts
+                    .appeal_bond_vault
+                    .as_ref()
+                    .ok_or(P2PError::MissingTokenAccount)?;
+                let vote_bond_vault = ctx
+                    .accounts
+                    .vote_bond_vault
+                    .as_ref()
+                    .ok_or(P2PError::MissingTokenAccount)?;
+                require!(
+                    appeal.voting_closed(clock.unix_timestamp),
+                    P2PError::AppealVotingOpen
+                );
+                let majority = appeal.majority();
+                let original_ruling = appeal.original_ruling;
+                settle_panel(&appeal.panel, &appeal.votes, ctx.remaining_accounts)?;
+
+                // 提交承诺但未揭示的仲裁员, 其投票保证金没收至国库
+                for vote in appeal.votes.iter().filter(|v| v.ruling.is_none()) {
+                    emit!(ArbitratorSlashed {
+                        order_id,
+                        trade_id,
+                        arbitrator: vote.arbitrator,
+                        amount: appeal.vote_bond,
+                    });
+                }
+                if vote_bond_vault.amount > 0 {
+                    let cpi_accounts = Transfer {
+                        from: vote_bond_vault.to_account_info(),
+                        to: ctx.accounts.treasury.to_account_info(),
+                        authority: dispute.to_account_info(),
+                    };
+                    token::transfer(
+                        CpiContext::new_with_signer(
+                            ctx.accounts.token_program.to_account_info(),
+                            cpi_accounts,
+                            &[&dispute_seeds[..]],
+                        ),
+                        vote_bond_vault.amount,
+                    )?;
+                }
+
+                // 未形成多数意见则维持原裁决; 上诉方所得增加视为上诉成功, 保证金退还
+                let final_ruling = majority.unwrap_or(original_ruling);
+                let appellant_is_buyer = appeal.appellant == trade.buyer();
+                let appellant_won = final_ruling.party_bps(appellant_is_buyer)
+                    > original_ruling.party_bps(appellant_is_buyer);
+                appeal.status = if majority.is_some() {
+                    AppealStatus::Decided
+                } else {
+                    AppealStatus::Expired
+                };
+                appeal.final_ruling = Some(final_ruling);
+
+                let bond_destination = if !appellant_won {
+                    ctx.accounts.treasury.to_account_info()
+                } else if appellant_is_buyer {
+                    ctx.accounts.buyer_token_account.to_account_info()
+                } else {
+                    ctx.accounts.seller_token_account.to_account_info()
+                };
+                if appeal_bond_vault.amount > 0 {
+                    let cpi_accounts = Transfer {
+                        from: appeal_bond_vault.to_account_info(),
+                        to: bond_destination,
+                        authority: dispute.to_account_info(),
+                    };
+                    token::transfer(
+                        CpiContext::new_with_signer(
+                            ctx.accounts.token_program.to_account_info(),
+                            cpi_accounts,
+                            &[&dispute_seeds[..]],
+                        ),
+                        appeal_bond_vault.amount,
+                    )?;
+                }
+
+                emit!(AppealDecided {
+                    order_id,
+                    trade_id,
+                    appellant: appeal.appellant,
+                    ruling: final_ruling,
+                    appellant_won,
+                });
+                final_ruling
+            }
+            _ => return err!(P2PError::DisputeNotRuled),
+        };
+
         let escrow = &mut ctx.accounts.escrow;
+        let seeds = &[
+            b"escrow",
+            order_id_bytes.as_ref(),
+            trade_id_bytes.as_ref(),
+            &[escrow.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // 按托管实际余额拆分, 结算后托管清零
+        let (buyer_amount, seller_amount) = ruling.split(ctx.accounts.escrow_token_account.amount);
+        let payouts = [
+            (&ctx.accounts.buyer_token_account, buyer_amount),
+            (&ctx.accounts.seller_token_account, seller_amount),
+        ];
+        for (destination, amount) in payouts {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.escrow_token_account.to_account_info(),
+                to: destination.to_account_info(),
+                authority: escrow.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer,
+                ),
+                amount,
+            )?;
+        }
+
+        escrow.status = if buyer_amount == 0 && seller_amount > 0 {
+            EscrowStatus::Refunded
+        } else {
+            EscrowStatus::Released
+        };
+
+        // 发起方所得不少于一半视为胜诉, 保证金退还; 否则代币没收至国库, SOL 支付给指派仲裁员
+        let plaintiff_bps = ruling.party_bps(dispute.plaintiff == trade.buyer());
+        let plaintiff_won = plaintiff_bps >= Ruling::MAX_BPS / 2;
+        if dispute.bond_in_sol && dispute.bond_amount > 0 {
+            let bond_destination = if plaintiff_won {
+                ctx.accounts.plaintiff.as_ref()
+            } else {
+                ctx.accounts.arbitrator_wallet.as_ref()
+            }
+            .ok_or(P2PError::MissingBondRecipient)?;
+            dispute.sub_lamports(dispute.bond_amount)?;
+            bond_destination.add_lamports(dispute.bond_amount)?;
+        }
+        let bond_destination = if !plaintiff_won {
+            ctx.accounts.treasury.to_account_info()
+        } else if dispute.plaintiff == trade.buyer() {
+            ctx.accounts.buyer_token_account.to_account_info()
+        } else {
+            ctx.accounts.seller_token_account.to_account_info()
+        };
+        let bond_amount = ctx.accounts.bond_vault.amount;
+        if bond_amount > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.bond_vault.to_account_info(),
+                to: bond_destination,
+                authority: dispute.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    &[&dispute_seeds[..]],
+                ),
+                bond_amount,
+            )?;
+        }
+        dispute.bond_status = if plaintiff_won {
+            BondStatus::Refunded
+        } else {
+            BondStatus::Forfeited
+        };
+
+        dispute.status = DisputeStatus::Resolved;
+        dispute.ruling = Some(ruling);
+        dispute.resolved_at = Some(clock.unix_timestamp);
+
+        trade.status = OrderStatus::Arbitrated;
+
+        // 所得少于一半的一方为败诉方, 扣除信誉分; 五五分成不扣分
+        let loser = match plaintiff_bps.cmp(&(Ruling::MAX_BPS / 2)) {
+            std::cmp::Ordering::Less => Some(dispute.plaintiff),
+            std::cmp::Ordering::Greater => Some(dispute.defendant),
+            std::cmp::Ordering::Equal => None,
+        };
+        if let Some(loser) = loser {
+            let (profile, history) = if loser == trade.maker {
+                (
+                    &mut ctx.accounts.maker_profile,
+                    &mut ctx.accounts.maker_history,
+                )
+            } else {
+                (
+                    &mut ctx.accounts.taker_profile,
+                    &mut ctx.accounts.taker_history,
+                )
+            };
+            let config = &ctx.accounts.platform_config;
+            profile.apply_decay(history, config.reputation_half_life, clock.unix_timestamp);
+            let penalty = config.dispute_penalty;
+            profile.change_reputation(
+                history,
+                -(penalty.min(i32::MAX as u32) as i32),
+                ReputationReason::DisputeLost,
+                Some(order_id),
+                clock.unix_timestamp,
+            );
+        }
+
+        emit!(DisputeResolved {
+            order_id,
+            trade_id,
+            ruling,
+            buyer_amount,
+            seller_amount,
+            bond_status: dispute.bond_status,
+        });
+        Ok(())
+    }
+
+    /// 成交完成后评价对手方, 每方仅可评价一次
+    pub fn rate_counterparty(
+        ctx: Context<RateCounterparty>,
+        order_id: u64,
+        trade_id: u64,
+        positive: bool,
+        comment_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        let rater = ctx.accounts.rater.key();
+        require!(
+            rater == trade.maker || rater == trade.taker,
+            P2PError::NotParticipant
+        );
+        require!(
+            trade.status == OrderStatus::Completed,
+            P2PError::TradeNotCompleted
+        );
+
+        let clock = Clock::get()?;
+        let completed_at = trade.completed_at.ok_or(P2PError::TradeNotCompleted)?;
+        require!(
+            clock.unix_timestamp < completed_at + ctx.accounts.platform_config.rating_window,
+            P2PError::RatingWindowClosed
+        );
+        let ratee = trade.counterparty(&rater);
+        require!(ratee != rater, P2PError::SelfTrade);
+        let rating = &mut ctx.accounts.rating;
+        rating.order_id = order_id;
+        rating.trade_id = trade_id;
+        rating.rater = rater;
+        rating.ratee = ratee;
+        rating.positive = positive;
+        rating.comment_hash = comment_hash;
+        rating.created_at = clock.unix_timestamp;
+        rating.bump = ctx.bumps.rating;
+
+        let profile = &mut ctx.accounts.ratee_profile;
+        if positive {
+            profile.positive_ratings += 1;
+        } else {
+            profile.negative_ratings += 1;
+        }
+        profile.update_positive_rate();
+        profile.apply_decay(
+            &mut ctx.accounts.ratee_history,
+            ctx.accounts.platform_config.reputation_half_life,
+            clock.unix_timestamp,
+        );
+
+        emit!(CounterpartyRated {
+            order_id,
+            trade_id,
+            rater,
+            ratee,
+            positive,
+            comment_hash,
+        });
+        Ok(())
+    }
+
+    /// 关闭已结束的成交, 回收托管及账户租金
+    pub fn close_trade(ctx: Context<CloseTrade>, order_id: u64, trade_id: u64) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        let is_finished = matches!(
+            trade.status,
+            OrderStatus::Completed | OrderStatus::Cancelled | OrderStatus::Arbitrated
+        );
+        require!(is_finished, P2PError::TradeNotFinished);
+        // 评价依赖成交记录, 评价期内不可关闭
+        if let Some(completed_at) = trade.completed_at {
+            require!(
+                Clock::get()?.unix_timestamp
+                    >= completed_at + ctx.accounts.platform_config.rating_window,
+                P2PError::RatingWindowOpen
+            );
+        }
+        require!(
+            ctx.accounts.escrow_token_account.amount == 0,
+            P2PError::EscrowNotEmpty
+        );
+        if trade.status == OrderStatus::Arbitrated {
+            require!(ctx.accounts.dispute.is_some(), P2PError::DisputeNotOpen);
+        }
+
+        let escrow = &ctx.accounts.escrow;
         let order_id_bytes = order_id.to_le_bytes();
-        let seeds = &[b"escrow", order_id_bytes.as_ref(), &[escrow.bump]];
+        let trade_id_bytes = trade_id.to_le_bytes();
+        let seeds = &[
+            b"escrow",
+            order_id_bytes.as_ref(),
+            trade_id_bytes.as_ref(),
+            &[escrow.bump],
+        ];
         let signer = &[&seeds[..]];
 
-        let transfer_amount = match &ruling {
-            Ruling::FavorBuyer => order.amount,
-            Ruling::FavorSeller => order.amount,
-            Ruling::Split => order.amount / 2,
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_token_account.to_account_info(),
+            destination: ctx.accounts.taker.to_account_info(),
+            authority: escrow.to_account_info(),
         };
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        ))?;
+
+        // 争议账户及保证金账户由发起方支付, 租金退回发起方
+        if let Some(dispute) = &ctx.accounts.dispute {
+            require!(
+                dispute.status == DisputeStatus::Resolved,
+                P2PError::TradeNotFinished
+            );
+            let bond_vault = ctx
+                .accounts
+                .bond_vault
+                .as_ref()
+                .ok_or(P2PError::MissingTokenAccount)?;
+            require!(bond_vault.amount == 0, P2PError::EscrowNotEmpty);
+
+            let plaintiff = if dispute.plaintiff == trade.maker {
+                ctx.accounts.maker.to_account_info()
+            } else {
+                ctx.accounts.taker.to_account_info()
+            };
+
+            let dispute_seeds = &[
+                b"dispute",
+                order_id_bytes.as_ref(),
+                trade_id_bytes.as_ref(),
+                &[dispute.bump],
+            ];
+            let cpi_accounts = CloseAccount {
+                account: bond_vault.to_account_info(),
+                destination: plaintiff.clone(),
+                authority: dispute.to_account_info(),
+            };
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                &[&dispute_seeds[..]],
+            ))?;
+
+            // 上诉账户及上诉/投票保证金账户由上诉方支付, 租金退回上诉方
+            if dispute.appealed {
+                let appeal = ctx
+                    .accounts
+                    .appeal
+                    .as_ref()
+                    .ok_or(P2PError::AppealNotFound)?;
+                let appeal_bond_vault = ctx
+                    .accounts
+                    .appeal_bond_vault
+                    .as_ref()
+                    .ok_or(P2PError::MissingTokenAccount)?;
+                require!(appeal_bond_vault.amount == 0, P2PError::EscrowNotEmpty);
+
+                let appellant = if appeal.appellant == trade.maker {
+                    ctx.accounts.maker.to_account_info()
+                } else {
+                    ctx.accounts.taker.to_account_info()
+                };
+
+                let cpi_accounts = CloseAccount {
+                    account: appeal_bond_vault.to_account_info(),
+                    destination: appellant.clone(),
+                    authority: dispute.to_account_info(),
+                };
+                token::close_account(CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    &[&dispute_seeds[..]],
+                ))?;
+
+                let vote_bond_vault = ctx
+                    .accounts
+                    .vote_bond_vault
+                    .as_ref()
+                    .ok_or(P2PError::MissingTokenAccount)?;
+                require!(vote_bond_vault.amount == 0, P2PError::EscrowNotEmpty);
+                let cpi_accounts = CloseAccount {
+                    account: vote_bond_vault.to_account_info(),
+                    destination: appellant.clone(),
+                    authority: dispute.to_account_info(),
+                };
+                token::close_account(CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    &[&dispute_seeds[..]],
+                ))?;
+
+                appeal.close(appellant)?;
+            }
+
+            dispute.close(plaintiff)?;
+        }
+
+        emit!(TradeClosed { order_id, trade_id });
+        Ok(())
+    }
+
+    /// 关闭已结束的广告, 回收广告托管及账户租金
+    pub fn close_order(ctx: Context<CloseOrder>, order_id: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let is_finished = order.status == OrderStatus::Cancelled
+            || (order.status == OrderStatus::Completed && order.remaining_amount == 0);
+        require!(is_finished, P2PError::OrderNotFinished);
+
+        if let Some(order_vault) = &ctx.accounts.order_vault {
+            require!(order_vault.amount == 0, P2PError::EscrowNotEmpty);
+
+            let order_id_bytes = order_id.to_le_bytes();
+            let seeds = &[b"order", order_id_bytes.as_ref(), &[order.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = CloseAccount {
+                account: order_vault.to_account_info(),
+                destination: ctx.accounts.maker.to_account_info(),
+                authority: order.to_account_info(),
+            };
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            ))?;
+        }
+
+        emit!(OrderClosed { order_id });
+        Ok(())
+    }
+
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.fee_manager,
+            P2PError::NotAuthorized
+        );
+
+        emit!(TreasuryInitialized {
+            token_mint: ctx.accounts.token_mint.key(),
+            treasury: ctx.accounts.treasury.key(),
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.fee_manager,
+            P2PError::NotAuthorized
+        );
+        require!(amount > 0, P2PError::InvalidAmount);
+        require!(
+            ctx.accounts.treasury.amount >= amount,
+            P2PError::InsufficientBalance
+        );
+
+        let seeds = &[
+            b"platform_config".as_ref(),
+            &[ctx.accounts.platform_config.bump],
+        ];
+        let signer = &[&seeds[..]];
 
         let cpi_accounts = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.winner_token_account.to_account_info(),
-            authority: escrow.to_account_info(),
+            from: ctx.accounts.treasury.to_account_info(),
+            to: ctx.accounts.destination.to_account_info(),
+            authority: ctx.accounts.platform_config.to_account_info(),
         };
         token::transfer(
             CpiContext::new_with_signer(
@@ -492,37 +1912,305 @@ pub mod tpot_p2p {
                 cpi_accounts,
                 signer,
             ),
-            transfer_amount,
+            amount,
         )?;
 
-        escrow.status = match &ruling {
-            Ruling::FavorBuyer => EscrowStatus::Released,
-            Ruling::FavorSeller => EscrowStatus::Refunded,
-            Ruling::Split => EscrowStatus::Released,
+        emit!(FeesWithdrawn {
+            token_mint: ctx.accounts.token_mint.key(),
+            destination: ctx.accounts.destination.key(),
+            authority: ctx.accounts.authority.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+        require!(
+            params.dispute_fee <= MAX_DISPUTE_FEE
+                && params.appeal_fee <= MAX_DISPUTE_FEE
+                && params.vote_bond_fee <= MAX_DISPUTE_FEE,
+            P2PError::InvalidFeeRate
+        );
+        require!(params.order_expiry > 0, P2PError::InvalidOrderExpiry);
+        require!(
+            params.min_payment_window > 0 && params.min_payment_window <= params.max_payment_window,
+            P2PError::InvalidPaymentWindow
+        );
+        require!(
+            params.auto_release_window > 0,
+            P2PError::InvalidAutoReleaseWindow
+        );
+        require!(
+            params.min_order_amount > 0 && params.min_order_amount <= params.max_order_amount,
+            P2PError::InvalidLimit
+        );
+        require!(
+            params.dispute_response_window > 0,
+            P2PError::InvalidResponseWindow
+        );
+        require!(
+            params.appeal_window >= 0
+                && params.appeal_commit_window > 0
+                && params.appeal_reveal_window > 0,
+            P2PError::InvalidAppealWindow
+        );
+        require!(params.rating_window >= 0, P2PError::InvalidRatingWindow);
+        require!(
+            params.reputation_volume_unit > 0 && params.reputation_half_life >= 0,
+            P2PError::InvalidReputationParams
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        let old = config.params();
+        config.apply_params(&params);
+
+        emit!(ConfigUpdated { old, new: params });
+        Ok(())
+    }
+
+    pub fn update_fee_schedule(
+        ctx: Context<UpdateFeeSchedule>,
+        fee_schedule: [u64; 5],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.fee_manager,
+            P2PError::NotAuthorized
+        );
+        require!(
+            fee_schedule.iter().all(|rate| *rate <= MAX_PLATFORM_FEE),
+            P2PError::InvalidFeeRate
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        let old_schedule = config.fee_schedule;
+        config.fee_schedule = fee_schedule;
+
+        emit!(FeeScheduleUpdated {
+            old_schedule,
+            new_schedule: fee_schedule,
+        });
+        Ok(())
+    }
+
+    /// 提议新管理员, 需新管理员调用 accept_authority 生效
+    pub fn propose_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        ctx.accounts.platform_config.pending_authority = Some(new_authority);
+
+        emit!(AuthorityProposed {
+            authority: ctx.accounts.authority.key(),
+            pending_authority: new_authority,
+        });
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+        require!(
+            config.pending_authority == Some(ctx.accounts.authority.key()),
+            P2PError::NotPendingAuthority
+        );
+
+        let old_authority = config.authority;
+        config.authority = ctx.accounts.authority.key();
+        config.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            old_authority,
+            new_authority: config.authority,
+        });
+        Ok(())
+    }
+
+    pub fn set_role(ctx: Context<UpdateConfig>, role: Role, account: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        let slot = match role {
+            Role::Pauser => &mut config.pauser,
+            Role::FeeManager => &mut config.fee_manager,
         };
+        let old = *slot;
+        *slot = account;
 
-        dispute.status = DisputeStatus::Resolved;
-        dispute.ruling = Some(ruling.clone());
-        dispute.resolved_at = Some(clock.unix_timestamp);
+        emit!(RoleUpdated {
+            role,
+            old,
+            new: account,
+        });
+        Ok(())
+    }
+
+    /// 登记仲裁员, max_amount 为其可受理的最大成交数量 (分级)
+    pub fn add_arbitrator(
+        ctx: Context<AddArbitrator>,
+        wallet: Pubkey,
+        max_amount: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        require!(
+            !config.is_arbitrator(&wallet),
+            P2PError::ArbitratorAlreadyExists
+        );
+        require!(
+            config.arbitrators.len() < MAX_ARBITRATORS,
+            P2PError::TooManyArbitrators
+        );
+        config.arbitrators.push(wallet);
+
+        let arbitrator = &mut ctx.accounts.arbitrator;
+        arbitrator.wallet = wallet;
+        arbitrator.max_amount = max_amount;
+        arbitrator.status = ArbitratorStatus::Active;
+        arbitrator.active_cases = 0;
+        arbitrator.resolved_cases = 0;
+        arbitrator.created_at = Clock::get()?.unix_timestamp;
+        arbitrator.bump = ctx.bumps.arbitrator;
+
+        emit!(ArbitratorAdded {
+            arbitrator: wallet,
+            max_amount,
+        });
+        Ok(())
+    }
+
+    pub fn remove_arbitrator(ctx: Context<RemoveArbitrator>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        let wallet = ctx.accounts.arbitrator.wallet;
+        require!(
+            ctx.accounts.arbitrator.active_cases == 0,
+            P2PError::ArbitratorHasActiveCases
+        );
+
+        let config = &mut ctx.accounts.platform_config;
+        config.arbitrators.retain(|a| *a != wallet);
 
-        order.status = OrderStatus::Arbitrated;
+        emit!(ArbitratorRemoved { arbitrator: wallet });
+        Ok(())
+    }
 
-        emit!(DisputeResolved { order_id, ruling });
+    /// 停用/恢复仲裁员或调整其受理额度, 停用后不再被指派新争议
+    pub fn update_arbitrator(
+        ctx: Context<UpdateArbitrator>,
+        status: ArbitratorStatus,
+        max_amount: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        let arbitrator = &mut ctx.accounts.arbitrator;
+        arbitrator.status = status;
+        arbitrator.max_amount = max_amount;
+
+        emit!(ArbitratorUpdated {
+            arbitrator: arbitrator.wallet,
+            status,
+            max_amount,
+        });
         Ok(())
     }
 
-    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+    /// 管理员调整用户信誉分, 计入信誉历史
+    pub fn adjust_reputation(
+        ctx: Context<AdjustReputation>,
+        user: Pubkey,
+        change: i32,
+    ) -> Result<()> {
         require!(
             ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
             P2PError::NotAuthorized
         );
+
+        let now = Clock::get()?.unix_timestamp;
+        let profile = &mut ctx.accounts.profile;
+        let history = &mut ctx.accounts.reputation_history;
+        profile.apply_decay(
+            history,
+            ctx.accounts.platform_config.reputation_half_life,
+            now,
+        );
+        profile.change_reputation(
+            history,
+            change,
+            ReputationReason::AdminAdjustment,
+            None,
+            now,
+        );
+
+        emit!(ReputationAdjusted {
+            user,
+            authority: ctx.accounts.authority.key(),
+            change,
+        });
+        Ok(())
+    }
+
+    /// 管理员设置用户 KYC 等级、认证与封禁状态
+    pub fn set_user_status(
+        ctx: Context<SetUserStatus>,
+        user: Pubkey,
+        kyc_level: u8,
+        is_verified: bool,
+        is_banned: bool,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            P2PError::NotAuthorized
+        );
+
+        let profile = &mut ctx.accounts.profile;
+        profile.kyc_level = kyc_level;
+        profile.is_verified = is_verified;
+        profile.is_banned = is_banned;
+        profile.apply_decay(
+            &mut ctx.accounts.reputation_history,
+            ctx.accounts.platform_config.reputation_half_life,
+            Clock::get()?.unix_timestamp,
+        );
+
+        emit!(UserStatusUpdated {
+            user,
+            kyc_level,
+            is_verified,
+            is_banned,
+        });
+        Ok(())
+    }
+
+    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.pauser,
+            P2PError::NotAuthorized
+        );
         ctx.accounts.platform_config.paused = true;
         Ok(())
     }
 
     pub fn resume(ctx: Context<Resume>) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == ctx.accounts.platform_config.authority,
+            ctx.accounts.authority.key() == ctx.accounts.platform_config.pauser,
             P2PError::NotAuthorized
         );
         ctx.accounts.platform_config.paused = false;
@@ -1,16 +1,19 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
-use crate::{EscrowAccount, PlatformConfig, UserProfile};
+use crate::{
+    sorted_pair, Appeal, Dispute, EscrowAccount, P2PError, PlatformConfig, ReputationHistory,
+    TradePair, UserProfile,
+};
 
 #[account]
 pub struct Order {
     pub order_id: u64,
     pub maker: Pubkey,
-    pub taker: Option<Pubkey>,
     pub order_type: OrderType,
     pub token_mint: Pubkey,
     pub amount: u64,
+    pub remaining_amount: u64,
     pub price: u64,
     pub payment_method: String,
     pub status: OrderStatus,
@@ -18,11 +21,83 @@ pub struct Order {
     pub expires_at: i64,
     pub min_limit: u64,
     pub max_limit: u64,
+    pub payment_window: i64,
+    pub trade_count: u64,
+    pub requirements: OrderRequirements,
     pub bump: u8,
 }
 
 impl Order {
-    pub const SIZE: usize = 8 + 8 + 32 + 1 + 32 + 1 + 32 + 8 + 8 + 4 + 16 + 1 + 8 + 8 + 8 + 8 + 1;
+    pub const SIZE: usize = 8
+        + 8
+        + 32
+        + 1
+        + 32
+        + 8
+        + 8
+        + 8
+        + 4
+        + 16
+        + 1
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + OrderRequirements::SIZE
+        + 1;
+
+    /// 剩余数量是否还能满足最小限额
+    pub fn is_fillable(&self) -> bool {
+        self.remaining_amount > 0 && self.remaining_amount >= self.min_limit
+    }
+}
+
+/// 成交记录 (每次接单生成一笔)
+#[account]
+pub struct Trade {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub order_type: OrderType,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub price: u64,
+    pub status: OrderStatus,
+    pub created_at: i64,
+    pub payment_deadline: i64,
+    pub paid_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Trade {
+    pub const SIZE: usize = 8 + 8 + 32 + 32 + 1 + 32 + 8 + 8 + 1 + 8 + 8 + 1 + 8 + 1 + 8 + 1;
+
+    pub fn buyer(&self) -> Pubkey {
+        match self.order_type {
+            OrderType::Buy => self.maker,
+            OrderType::Sell => self.taker,
+        }
+    }
+
+    pub fn seller(&self) -> Pubkey {
+        match self.order_type {
+            OrderType::Buy => self.taker,
+            OrderType::Sell => self.maker,
+        }
+    }
+
+    /// 成交对手方
+    pub fn counterparty(&self, key: &Pubkey) -> Pubkey {
+        if *key == self.maker {
+            self.taker
+        } else {
+            self.maker
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -43,6 +118,37 @@ pub enum OrderStatus {
     Arbitrated,
 }
 
+/// 挂单方对接单方的准入要求
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct OrderRequirements {
+    pub min_level: u8,             // 最低信誉等级
+    pub min_completed_trades: u32, // 最少完成交易数
+    pub min_kyc_level: u8,         // 最低KYC等级
+    pub verified_only: bool,       // 仅限认证用户
+}
+
+impl OrderRequirements {
+    pub const SIZE: usize = 1 + 4 + 1 + 1;
+
+    /// 校验接单方是否满足准入要求
+    pub fn check(&self, profile: &UserProfile) -> Result<()> {
+        require!(
+            profile.get_level() >= self.min_level
+                && profile.completed_trades >= self.min_completed_trades,
+            P2PError::InsufficientReputation
+        );
+        require!(
+            profile.kyc_level >= self.min_kyc_level,
+            P2PError::InsufficientKycLevel
+        );
+        require!(
+            !self.verified_only || profile.is_verified,
+            P2PError::VerificationRequired
+        );
+        Ok(())
+    }
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -59,13 +165,18 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
 pub struct CreateBuyOrder<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
     #[account(
         init,
         payer = maker,
         space = 8 + Order::SIZE,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
+        seeds = [b"order", platform_config.next_order_id.to_le_bytes().as_ref()],
         bump
     )]
     pub order: Account<'info, Order>,
@@ -76,37 +187,44 @@ pub struct CreateBuyOrder<'info> {
         payer = maker,
         space = 8 + UserProfile::SIZE,
         seeds = [b"profile", maker.key().as_ref()],
-        bump
+        bump,
+        constraint = !maker_profile.is_banned @ P2PError::UserBanned
     )]
     pub maker_profile: Account<'info, UserProfile>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + ReputationHistory::SIZE,
+        seeds = [b"reputation_history", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
     pub token_mint: Account<'info, Mint>,
     #[account(
-        seeds = [b"platform_config"],
-        bump = platform_config.bump
+        seeds = [b"treasury", platform_config.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        constraint = treasury.owner == platform_config.key() @ P2PError::FeeTokenAccountMismatch
     )]
-    pub platform_config: Account<'info, PlatformConfig>,
+    pub treasury: Account<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
 pub struct CreateSellOrder<'info> {
     #[account(
-        init,
-        payer = maker,
-        space = 8 + Order::SIZE,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
     )]
-    pub order: Box<Account<'info, Order>>,
+    pub platform_config: Box<Account<'info, PlatformConfig>>,
     #[account(
         init,
         payer = maker,
-        space = 8 + EscrowAccount::SIZE,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
+        space = 8 + Order::SIZE,
+        seeds = [b"order", platform_config.next_order_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub escrow: Box<Account<'info, EscrowAccount>>,
+    pub order: Box<Account<'info, Order>>,
     #[account(mut)]
     pub maker: Signer<'info>,
     #[account(
@@ -114,42 +232,41 @@ pub struct CreateSellOrder<'info> {
         payer = maker,
         space = 8 + UserProfile::SIZE,
         seeds = [b"profile", maker.key().as_ref()],
-        bump
+        bump,
+        constraint = !maker_profile.is_banned @ P2PError::UserBanned
     )]
     pub maker_profile: Box<Account<'info, UserProfile>>,
-    pub token_mint: Account<'info, Mint>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-#[instruction(order_id: u64)]
-pub struct DepositEscrow<'info> {
     #[account(
-        mut,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump = order.bump
+        init_if_needed,
+        payer = maker,
+        space = 8 + ReputationHistory::SIZE,
+        seeds = [b"reputation_history", maker.key().as_ref()],
+        bump
     )]
-    pub order: Account<'info, Order>,
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
     #[account(
         mut,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
-        bump = escrow.bump
+        constraint = maker_token_account.owner == maker.key()
+            @ P2PError::SellerTokenAccountMismatch,
+        constraint = maker_token_account.mint == token_mint.key() @ P2PError::InvalidTokenMint
     )]
-    pub escrow: Account<'info, EscrowAccount>,
-    #[account(mut)]
-    pub maker: Signer<'info>,
-    #[account(mut)]
-    pub maker_token_account: Account<'info, TokenAccount>,
+    pub maker_token_account: Box<Account<'info, TokenAccount>>,
     #[account(
         init,
         payer = maker,
         token::mint = token_mint,
-        token::authority = escrow,
-        seeds = [b"escrow_token", order_id.to_le_bytes().as_ref()],
+        token::authority = order,
+        seeds = [b"order_vault", platform_config.next_order_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub order_vault: Box<Account<'info, TokenAccount>>,
     pub token_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"treasury", platform_config.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        constraint = treasury.owner == platform_config.key() @ P2PError::FeeTokenAccountMismatch
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
@@ -162,15 +279,57 @@ pub struct TakeOrder<'info> {
         seeds = [b"order", order_id.to_le_bytes().as_ref()],
         bump = order.bump
     )]
-    pub order: Account<'info, Order>,
+    pub order: Box<Account<'info, Order>>,
     #[account(
-        init_if_needed,
+        init,
+        payer = taker,
+        space = 8 + Trade::SIZE,
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            order.trade_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        init,
         payer = taker,
         space = 8 + EscrowAccount::SIZE,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            order.trade_count.to_le_bytes().as_ref()
+        ],
         bump
     )]
-    pub escrow: Account<'info, EscrowAccount>,
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    #[account(
+        init,
+        payer = taker,
+        token::mint = token_mint,
+        token::authority = escrow,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            order.trade_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"order_vault", order_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub order_vault: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        constraint = taker_token_account.owner == taker.key()
+            @ P2PError::SellerTokenAccountMismatch,
+        constraint = taker_token_account.mint == order.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub taker_token_account: Option<Box<Account<'info, TokenAccount>>>,
     #[account(mut)]
     pub taker: Signer<'info>,
     #[account(
@@ -178,64 +337,154 @@ pub struct TakeOrder<'info> {
         payer = taker,
         space = 8 + UserProfile::SIZE,
         seeds = [b"profile", taker.key().as_ref()],
+        bump,
+        constraint = !taker_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub taker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + ReputationHistory::SIZE,
+        seeds = [b"reputation_history", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"profile", order.maker.as_ref()],
+        bump = maker_profile.bump,
+        constraint = !maker_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub maker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + TradePair::SIZE,
+        seeds = [
+            b"trade_pair",
+            sorted_pair(order.maker, taker.key())[0].as_ref(),
+            sorted_pair(order.maker, taker.key())[1].as_ref()
+        ],
         bump
     )]
-    pub taker_profile: Account<'info, UserProfile>,
+    pub trade_pair: Box<Account<'info, TradePair>>,
+    #[account(address = order.token_mint)]
     pub token_mint: Account<'info, Mint>,
     #[account(
         seeds = [b"platform_config"],
         bump = platform_config.bump
     )]
-    pub platform_config: Account<'info, PlatformConfig>,
+    pub platform_config: Box<Account<'info, PlatformConfig>>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
+#[instruction(order_id: u64, trade_id: u64)]
 pub struct ConfirmPayment<'info> {
     #[account(
         mut,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump = order.bump
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
     )]
-    pub order: Account<'info, Order>,
+    pub trade: Account<'info, Trade>,
     pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"profile", payer.key().as_ref()],
+        bump = payer_profile.bump,
+        constraint = !payer_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub payer_profile: Account<'info, UserProfile>,
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
+#[instruction(order_id: u64, trade_id: u64)]
 pub struct ReleaseTokens<'info> {
     #[account(
         mut,
-        seeds = [b"order", order_id.to_le_bytes().as_ref()],
-        bump = order.bump
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
     )]
-    pub order: Account<'info, Order>,
+    pub trade: Box<Account<'info, Trade>>,
     #[account(
         mut,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
         bump = escrow.bump
     )]
-    pub escrow: Account<'info, EscrowAccount>,
+    pub escrow: Box<Account<'info, EscrowAccount>>,
     pub seller: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"profile", order.maker.as_ref()],
+        seeds = [b"profile", trade.maker.as_ref()],
         bump
     )]
-    pub maker_profile: Account<'info, UserProfile>,
+    pub maker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.maker.as_ref()],
+        bump = maker_history.bump
+    )]
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
     #[account(
         mut,
-        seeds = [b"profile", order.taker.unwrap().as_ref()],
+        seeds = [b"profile", trade.taker.as_ref()],
         bump
     )]
-    pub taker_profile: Account<'info, UserProfile>,
-    #[account(mut)]
+    pub taker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.taker.as_ref()],
+        bump = taker_history.bump
+    )]
+    pub taker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        mut,
+        seeds = [
+            b"trade_pair",
+            sorted_pair(trade.maker, trade.taker)[0].as_ref(),
+            sorted_pair(trade.maker, trade.taker)[1].as_ref()
+        ],
+        bump = trade_pair.bump
+    )]
+    pub trade_pair: Box<Account<'info, TradePair>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = escrow_token_account.owner == escrow.key()
+            @ P2PError::EscrowTokenAccountMismatch,
+        constraint = escrow_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
     pub escrow_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == trade.buyer()
+            @ P2PError::BuyerTokenAccountMismatch,
+        constraint = buyer_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
     pub buyer_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub fee_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"treasury", platform_config.key().as_ref(), trade.token_mint.as_ref()],
+        bump,
+        constraint = treasury.owner == platform_config.key() @ P2PError::FeeTokenAccountMismatch
+    )]
+    pub treasury: Account<'info, TokenAccount>,
     #[account(
         seeds = [b"platform_config"],
         bump = platform_config.bump
@@ -244,6 +493,163 @@ pub struct ReleaseTokens<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct AutoReleaseTokens<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.maker.as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.maker.as_ref()],
+        bump = maker_history.bump
+    )]
+    pub maker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.taker.as_ref()],
+        bump
+    )]
+    pub taker_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.taker.as_ref()],
+        bump = taker_history.bump
+    )]
+    pub taker_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        mut,
+        seeds = [
+            b"trade_pair",
+            sorted_pair(trade.maker, trade.taker)[0].as_ref(),
+            sorted_pair(trade.maker, trade.taker)[1].as_ref()
+        ],
+        bump = trade_pair.bump
+    )]
+    pub trade_pair: Box<Account<'info, TradePair>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = escrow_token_account.owner == escrow.key()
+            @ P2PError::EscrowTokenAccountMismatch,
+        constraint = escrow_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == trade.buyer()
+            @ P2PError::BuyerTokenAccountMismatch,
+        constraint = buyer_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"treasury", platform_config.key().as_ref(), trade.token_mint.as_ref()],
+        bump,
+        constraint = treasury.owner == platform_config.key() @ P2PError::FeeTokenAccountMismatch
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct CancelUnpaidTrade<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = escrow_token_account.owner == escrow.key()
+            @ P2PError::EscrowTokenAccountMismatch,
+        constraint = escrow_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == trade.seller()
+            @ P2PError::SellerTokenAccountMismatch,
+        constraint = seller_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.buyer().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Box<Account<'info, UserProfile>>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.buyer().as_ref()],
+        bump = buyer_history.bump
+    )]
+    pub buyer_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub caller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
 pub struct CancelOrder<'info> {
@@ -253,36 +659,204 @@ pub struct CancelOrder<'info> {
         bump = order.bump
     )]
     pub order: Account<'info, Order>,
-    #[account(
-        mut,
-        seeds = [b"escrow", order_id.to_le_bytes().as_ref()],
-        bump = escrow.bump
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
     pub canceler: Signer<'info>,
+    // 封禁用户仍可撤单取回托管, 不检查封禁状态
     #[account(
         mut,
         seeds = [b"profile", canceler.key().as_ref()],
         bump
     )]
     pub canceler_profile: Account<'info, UserProfile>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"reputation_history", canceler.key().as_ref()],
+        bump = canceler_history.bump
+    )]
+    pub canceler_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"order_vault", order_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub order_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == order.maker
+            @ P2PError::SellerTokenAccountMismatch,
+        constraint = seller_token_account.mint == order.token_mint @ P2PError::InvalidTokenMint
+    )]
+    pub seller_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct CloseTrade<'info> {
+    #[account(
+        mut,
+        close = taker,
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Box<Account<'info, Trade>>,
+    #[account(
+        mut,
+        close = taker,
+        seeds = [
+            b"escrow",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_token",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = escrow_token_account.owner == escrow.key()
+            @ P2PError::EscrowTokenAccountMismatch,
+        constraint = escrow_token_account.mint == trade.token_mint @ P2PError::InvalidTokenMint
+    )]
     pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = dispute.bump
+    )]
+    pub dispute: Option<Box<Account<'info, Dispute>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub bond_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"appeal",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = appeal.bump
+    )]
+    pub appeal: Option<Box<Account<'info, Appeal>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"appeal_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub appeal_bond_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"vote_bond",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub vote_bond_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut, address = trade.maker)]
+    pub maker: SystemAccount<'info>,
+    #[account(mut, address = trade.taker)]
+    pub taker: SystemAccount<'info>,
+    pub caller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CloseOrder<'info> {
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ P2PError::NotAuthorized,
+        seeds = [b"order", order_id.to_le_bytes().as_ref()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        mut,
+        seeds = [b"order_vault", order_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub order_vault: Option<Account<'info, TokenAccount>>,
     #[account(mut)]
-    pub seller_token_account: Account<'info, TokenAccount>,
+    pub maker: Signer<'info>,
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct Pause<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
     pub platform_config: Account<'info, PlatformConfig>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct Resume<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
     pub platform_config: Account<'info, PlatformConfig>,
     pub authority: Signer<'info>,
 }
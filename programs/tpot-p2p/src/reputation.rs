@@ -1,9 +1,19 @@
 use anchor_lang::prelude::*;
 
+use crate::{P2PError, PlatformConfig, ReputationUpdated, Trade};
+
+pub const MAX_USERNAME_LEN: usize = 32;
+pub const MAX_AVATAR_LEN: usize = 100;
+pub const REPUTATION_HISTORY_LEN: usize = 32;
+pub const DECAY_STEPS: i64 = 32; // 每个半衰期划分的衰减步数
+pub const DECAY_SCALE: u128 = 1_000_000_000_000; // 信誉衰减定点精度
+pub const DECAY_FACTOR: u128 = 978_572_062_088; // 每步衰减系数 2^(-1/32), 定点表示 (向上取整)
+
 // ============ 账户结构 ============
 
 /// 用户资料
 #[account]
+#[derive(Default)]
 pub struct UserProfile {
     pub wallet: Pubkey,           // 钱包地址
     pub username: Option<String>, // 用户名
@@ -16,6 +26,13 @@ pub struct UserProfile {
     pub cancelled_trades: u32,    // 取消交易数
     pub disputed_trades: u32,     // 争议交易数
     pub completion_rate: u32,     // 完成率 (基点)
+    pub positive_ratings: u32,    // 好评数
+    pub negative_ratings: u32,    // 差评数
+    pub positive_rate: u32,       // 好评率 (基点)
+    pub gain_day: i64,            // 信誉增长计数所在日
+    pub gain_today: u32,          // 当日已增长信誉分
+    pub decayed_at: i64,          // 信誉衰减已结算至
+    pub reputation_dust: u64,     // 衰减后不足 1 分的余数 (定点)
     pub created_at: i64,          // 创建时间
     pub updated_at: i64,          // 更新时间
     pub is_verified: bool,        // 是否认证
@@ -24,8 +41,40 @@ pub struct UserProfile {
 }
 
 impl UserProfile {
-    pub const SIZE: usize =
-        32 + 1 + 4 + 16 + 1 + 4 + 32 + 1 + 4 + 4 + 4 + 4 + 4 + 4 + 8 + 8 + 1 + 1 + 1;
+    pub const SIZE: usize = 32 +    // wallet
+        1 + 4 + MAX_USERNAME_LEN +  // username
+        1 + 4 + MAX_AVATAR_LEN +    // avatar
+        1 +                         // kyc_level
+        4 +                         // reputation
+        4 +                         // total_trades
+        4 +                         // total_orders
+        4 +                         // completed_trades
+        4 +                         // cancelled_trades
+        4 +                         // disputed_trades
+        4 +                         // completion_rate
+        4 +                         // positive_ratings
+        4 +                         // negative_ratings
+        4 +                         // positive_rate
+        8 +                         // gain_day
+        4 +                         // gain_today
+        8 +                         // decayed_at
+        8 +                         // reputation_dust
+        8 +                         // created_at
+        8 +                         // updated_at
+        1 +                         // is_verified
+        1 +                         // is_banned
+        1; // bump
+}
+
+/// 用户名登记, 保证用户名全局唯一
+#[account]
+pub struct UsernameRecord {
+    pub owner: Pubkey, // 持有者钱包
+    pub bump: u8,      // PDA bump
+}
+
+impl UsernameRecord {
+    pub const SIZE: usize = 32 + 1;
 }
 
 // ============ 辅助函数 ============
@@ -50,6 +99,14 @@ impl UserProfile {
         }
     }
 
+    /// 更新好评率
+    pub fn update_positive_rate(&mut self) {
+        let total = self.positive_ratings + self.negative_ratings;
+        if total > 0 {
+            self.positive_rate = (self.positive_ratings as u64 * 10000 / total as u64) as u32;
+        }
+    }
+
     /// 添加信誉分
     pub fn add_reputation(&mut self, points: u32) {
         self.reputation = self.reputation.saturating_add(points);
@@ -58,23 +115,516 @@ impl UserProfile {
     /// 扣除信誉分
     pub fn subtract_reputation(&mut self, points: u32) {
         self.reputation = self.reputation.saturating_sub(points);
+        if self.reputation == 0 {
+            self.reputation_dust = 0;
+        }
+    }
+
+    /// 按每日上限截断信誉增长, 返回实际可增长的分数
+    pub fn cap_daily_gain(&mut self, gain: u32, now: i64, daily_cap: u32) -> u32 {
+        let day = now / 86400;
+        if self.gain_day != day {
+            self.gain_day = day;
+            self.gain_today = 0;
+        }
+        let gain = gain.min(daily_cap.saturating_sub(self.gain_today));
+        self.gain_today += gain;
+        gain
+    }
+
+    /// 按半衰期衰减自上次结算以来的信誉分, 结算时间独立于 updated_at, 频繁变动不会跳过衰减
+    pub fn apply_decay(&mut self, history: &mut ReputationHistory, half_life: i64, now: i64) {
+        let step = half_life / DECAY_STEPS;
+        if step <= 0 || self.decayed_at == 0 {
+            self.decayed_at = now;
+        } else if now - self.decayed_at >= step {
+            // 只结算整数个衰减步, 不足一步的时间与不足 1 分的余数留待下次
+            let steps = (now - self.decayed_at) / step;
+            self.decayed_at += steps * step;
+            let scaled = self.reputation as u128 * DECAY_SCALE + self.reputation_dust as u128;
+            let decayed = decay_reputation(scaled, steps as u64);
+            self.reputation_dust = (decayed % DECAY_SCALE) as u64;
+            let change = (decayed / DECAY_SCALE) as i32 - self.reputation as i32;
+            if change < 0 {
+                self.change_reputation(history, change, ReputationReason::Decay, None, now);
+            }
+        }
+        self.updated_at = now;
+    }
+
+    /// 调整信誉分并写入信誉历史
+    pub fn change_reputation(
+        &mut self,
+        history: &mut ReputationHistory,
+        change: i32,
+        reason: ReputationReason,
+        order_id: Option<u64>,
+        now: i64,
+    ) {
+        let old_score = self.reputation;
+        if change >= 0 {
+            self.add_reputation(change as u32);
+        } else {
+            self.subtract_reputation(change.unsigned_abs());
+        }
+        let change = self.reputation as i32 - old_score as i32;
+
+        history.push(ReputationRecord {
+            change,
+            reason,
+            order_id,
+            new_score: self.reputation,
+            created_at: now,
+        });
+        self.updated_at = now;
+
+        emit!(ReputationUpdated {
+            user: self.wallet,
+            change,
+            new_score: self.reputation,
+        });
     }
 }
 
-/// 信誉记录
+/// 用户名仅允许小写字母、数字和下划线
+pub fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= MAX_USERNAME_LEN
+        && username
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// 成交评价, 每笔成交每方仅可评价一次
 #[account]
+pub struct Rating {
+    pub order_id: u64,                  // 关联订单
+    pub trade_id: u64,                  // 关联成交
+    pub rater: Pubkey,                  // 评价方
+    pub ratee: Pubkey,                  // 被评价方
+    pub positive: bool,                 // 是否好评
+    pub comment_hash: Option<[u8; 32]>, // 评价内容哈希
+    pub created_at: i64,                // 创建时间
+    pub bump: u8,                       // PDA bump
+}
+
+impl Rating {
+    pub const SIZE: usize = 8 +     // order_id
+        8 +                         // trade_id
+        32 +                        // rater
+        32 +                        // ratee
+        1 +                         // positive
+        1 + 32 +                    // comment_hash
+        8 +                         // created_at
+        1; // bump
+}
+
+/// 定点信誉分经过 steps 个衰减步后的值: 整数个半衰期逐次减半, 余下各步乘以 2^(-1/32);
+/// 32 个半衰期后任何分数都不足 1 分, 直接归零
+pub fn decay_reputation(scaled: u128, steps: u64) -> u128 {
+    let halvings = steps / DECAY_STEPS as u64;
+    if halvings >= 32 {
+        return 0;
+    }
+    let mut scaled = scaled >> halvings;
+    for _ in 0..steps % DECAY_STEPS as u64 {
+        // 向上取整, 使分次结算的结果不低于一次结算, 整半衰期处不会少算 1 分
+        scaled = (scaled * DECAY_FACTOR).div_ceil(DECAY_SCALE);
+    }
+    scaled
+}
+
+/// 交易对累计成交, 用于对重复对手方递减信誉增长
+#[account]
+pub struct TradePair {
+    pub traders: [Pubkey; 2],  // 按字节序排列的双方钱包
+    pub completed_trades: u32, // 已完成成交数
+    pub bump: u8,              // PDA bump
+}
+
+impl TradePair {
+    pub const SIZE: usize = 32 * 2 + 4 + 1;
+}
+
+/// 按字节序排列交易双方, 作为交易对 PDA 种子
+pub fn sorted_pair(a: Pubkey, b: Pubkey) -> [Pubkey; 2] {
+    if a <= b {
+        [a, b]
+    } else {
+        [b, a]
+    }
+}
+
+/// 信誉变动原因
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationReason {
+    TradeCompleted,  // 完成交易
+    TradeCancelled,  // 超时未付款被取消
+    OrderCancelled,  // 取消挂单
+    DisputeLost,     // 争议败诉
+    AdminAdjustment, // 管理员调整
+    Decay,           // 长期未活跃衰减
+}
+
+/// 信誉记录
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub struct ReputationRecord {
-    pub user: Pubkey,          // 用户
-    pub change: i32,           // 变化 (正数增加，负数减少)
-    pub reason: String,        // 原因
-    pub order_id: Option<u64>, // 关联订单
-    pub created_at: i64,       // 创建时间
+    pub change: i32,              // 变化 (正数增加，负数减少)
+    pub reason: ReputationReason, // 原因
+    pub order_id: Option<u64>,    // 关联订单
+    pub new_score: u32,           // 变动后信誉分
+    pub created_at: i64,          // 创建时间
 }
 
 impl ReputationRecord {
-    pub const SIZE: usize = 32 +    // user
-        4 +                         // change
-        4 + 100 +                   // reason
+    pub const SIZE: usize = 4 +     // change
+        1 +                         // reason
         1 + 8 +                     // order_id
+        4 +                         // new_score
         8; // created_at
 }
+
+/// 信誉历史 (环形缓冲, 保留最近 REPUTATION_HISTORY_LEN 条)
+#[account]
+#[derive(Default)]
+pub struct ReputationHistory {
+    pub user: Pubkey,                   // 用户
+    pub head: u16,                      // 下一条写入位置
+    pub total: u64,                     // 累计记录数
+    pub records: Vec<ReputationRecord>, // 记录
+    pub bump: u8,                       // PDA bump
+}
+
+impl ReputationHistory {
+    pub const SIZE: usize = 32 +    // user
+        2 +                         // head
+        8 +                         // total
+        4 + ReputationRecord::SIZE * REPUTATION_HISTORY_LEN + // records
+        1; // bump
+
+    pub fn push(&mut self, record: ReputationRecord) {
+        if self.records.len() < REPUTATION_HISTORY_LEN {
+            self.records.push(record);
+        } else {
+            self.records[self.head as usize] = record;
+        }
+        self.head = ((self.head as usize + 1) % REPUTATION_HISTORY_LEN) as u16;
+        self.total += 1;
+    }
+}
+
+// ============ 指令账户结构 ============
+
+/// 创建用户资料 (已因交易自动创建的资料可在此认领用户名)
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct CreateProfile<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserProfile::SIZE,
+        seeds = [b"profile", user.key().as_ref()],
+        bump,
+        constraint = !profile.is_banned @ P2PError::UserBanned
+    )]
+    pub profile: Account<'info, UserProfile>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReputationHistory::SIZE,
+        seeds = [b"reputation_history", user.key().as_ref()],
+        bump
+    )]
+    pub reputation_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UsernameRecord::SIZE,
+        seeds = [b"username", username.as_bytes()],
+        bump
+    )]
+    pub username_record: Account<'info, UsernameRecord>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// 更新用户资料
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", user.key().as_ref()],
+        bump = profile.bump,
+        constraint = !profile.is_banned @ P2PError::UserBanned
+    )]
+    pub profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", user.key().as_ref()],
+        bump = reputation_history.bump
+    )]
+    pub reputation_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    pub user: Signer<'info>,
+}
+
+/// 更换用户名, 释放旧用户名
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct ChangeUsername<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", user.key().as_ref()],
+        bump = profile.bump,
+        constraint = !profile.is_banned @ P2PError::UserBanned
+    )]
+    pub profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", user.key().as_ref()],
+        bump = reputation_history.bump
+    )]
+    pub reputation_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            b"username",
+            profile.username.as_deref().unwrap_or_default().as_bytes()
+        ],
+        bump = old_username_record.bump,
+        constraint = old_username_record.owner == user.key() @ P2PError::NotAuthorized
+    )]
+    pub old_username_record: Account<'info, UsernameRecord>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UsernameRecord::SIZE,
+        seeds = [b"username", username.as_bytes()],
+        bump
+    )]
+    pub new_username_record: Account<'info, UsernameRecord>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// 评价成交对手方
+#[derive(Accounts)]
+#[instruction(order_id: u64, trade_id: u64)]
+pub struct RateCounterparty<'info> {
+    #[account(
+        seeds = [
+            b"trade",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref()
+        ],
+        bump = trade.bump
+    )]
+    pub trade: Account<'info, Trade>,
+    #[account(
+        init,
+        payer = rater,
+        space = 8 + Rating::SIZE,
+        seeds = [
+            b"rating",
+            order_id.to_le_bytes().as_ref(),
+            trade_id.to_le_bytes().as_ref(),
+            rater.key().as_ref()
+        ],
+        bump
+    )]
+    pub rating: Account<'info, Rating>,
+    #[account(
+        mut,
+        seeds = [b"profile", trade.counterparty(&rater.key()).as_ref()],
+        bump = ratee_profile.bump
+    )]
+    pub ratee_profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", trade.counterparty(&rater.key()).as_ref()],
+        bump = ratee_history.bump
+    )]
+    pub ratee_history: Box<Account<'info, ReputationHistory>>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(mut)]
+    pub rater: Signer<'info>,
+    #[account(
+        seeds = [b"profile", rater.key().as_ref()],
+        bump = rater_profile.bump,
+        constraint = !rater_profile.is_banned @ P2PError::UserBanned
+    )]
+    pub rater_profile: Account<'info, UserProfile>,
+    pub system_program: Program<'info, System>,
+}
+
+/// 管理员调整用户信誉分
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AdjustReputation<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"profile", user.as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", user.as_ref()],
+        bump = reputation_history.bump
+    )]
+    pub reputation_history: Box<Account<'info, ReputationHistory>>,
+    pub authority: Signer<'info>,
+}
+
+/// 管理员设置用户状态
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct SetUserStatus<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"profile", user.as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        seeds = [b"reputation_history", user.as_ref()],
+        bump = reputation_history.bump
+    )]
+    pub reputation_history: Box<Account<'info, ReputationHistory>>,
+    pub authority: Signer<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86400;
+
+    #[test]
+    fn daily_cap_limits_gain_and_resets_next_day() {
+        let mut profile = UserProfile::default();
+        assert_eq!(profile.cap_daily_gain(30, DAY, 50), 30);
+        assert_eq!(profile.cap_daily_gain(30, DAY + 100, 50), 20);
+        assert_eq!(profile.cap_daily_gain(30, 2 * DAY - 1, 50), 0);
+        assert_eq!(profile.cap_daily_gain(30, 2 * DAY, 50), 30);
+    }
+
+    #[test]
+    fn history_overwrites_oldest_record_when_full() {
+        let record = |i: usize| ReputationRecord {
+            change: 1,
+            reason: ReputationReason::TradeCompleted,
+            order_id: Some(i as u64),
+            new_score: i as u32,
+            created_at: i as i64,
+        };
+        let mut history = ReputationHistory::default();
+        for i in 0..REPUTATION_HISTORY_LEN {
+            history.push(record(i));
+        }
+        assert_eq!(history.records.len(), REPUTATION_HISTORY_LEN);
+        assert_eq!(history.head, 0);
+        assert_eq!(history.total, REPUTATION_HISTORY_LEN as u64);
+
+        history.push(record(REPUTATION_HISTORY_LEN));
+        history.push(record(REPUTATION_HISTORY_LEN + 1));
+        assert_eq!(history.records.len(), REPUTATION_HISTORY_LEN);
+        assert_eq!(history.head, 2);
+        assert_eq!(history.total, REPUTATION_HISTORY_LEN as u64 + 2);
+        assert_eq!(history.records[0].new_score, REPUTATION_HISTORY_LEN as u32);
+        assert_eq!(
+            history.records[1].new_score,
+            REPUTATION_HISTORY_LEN as u32 + 1
+        );
+        assert_eq!(history.records[2].new_score, 2);
+    }
+
+    #[test]
+    fn decay_halves_per_half_life() {
+        let score = 500 * DECAY_SCALE;
+        assert_eq!(decay_reputation(score, 0), score);
+        assert_eq!(
+            decay_reputation(score, DECAY_STEPS as u64),
+            250 * DECAY_SCALE
+        );
+        assert_eq!(
+            decay_reputation(score, 2 * DECAY_STEPS as u64),
+            125 * DECAY_SCALE
+        );
+        let half_step = decay_reputation(score, DECAY_STEPS as u64 / 2) / DECAY_SCALE;
+        assert_eq!(half_step, 353);
+    }
+
+    #[test]
+    fn decay_zeroes_after_32_halvings() {
+        let score = u32::MAX as u128 * DECAY_SCALE;
+        assert_eq!(
+            decay_reputation(score, 31 * DECAY_STEPS as u64) / DECAY_SCALE,
+            1
+        );
+        assert_eq!(decay_reputation(score, 32 * DECAY_STEPS as u64), 0);
+        assert_eq!(decay_reputation(score, u64::MAX), 0);
+    }
+
+    #[test]
+    fn frequent_touches_decay_like_a_single_touch() {
+        let half_life = 180 * DAY;
+        let start = DAY;
+        let new_profile = || UserProfile {
+            reputation: 500,
+            decayed_at: start,
+            ..Default::default()
+        };
+
+        let mut once = new_profile();
+        once.apply_decay(
+            &mut ReputationHistory::default(),
+            half_life,
+            start + half_life,
+        );
+        assert_eq!(once.reputation, 250);
+
+        let mut hourly = new_profile();
+        let mut history = ReputationHistory::default();
+        let mut now = start;
+        while now < start + half_life {
+            now += 3600;
+            hourly.apply_decay(&mut history, half_life, now);
+        }
+        assert_eq!(hourly.reputation, 250);
+        assert_eq!(hourly.decayed_at, start + half_life);
+        assert_eq!(hourly.updated_at, start + half_life);
+    }
+}
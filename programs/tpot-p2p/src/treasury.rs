@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::{P2PError, PlatformConfig};
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init,
+        payer = authority,
+        token::mint = token_mint,
+        token::authority = platform_config,
+        seeds = [b"treasury", platform_config.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    pub token_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"treasury", platform_config.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.mint == token_mint.key() @ P2PError::InvalidTokenMint
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_mint: Account<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
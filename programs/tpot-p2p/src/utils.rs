@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::{OrderType, Ruling};
+use crate::{
+    Arbitrator, ArbitratorStatus, BondStatus, ConfigParams, EscrowAccount, EscrowStatus,
+    OrderStatus, OrderType, PanelVote, PlatformConfig, ReputationHistory, ReputationReason, Role,
+    Ruling, Trade, TradePair, UserProfile, APPEAL_PANEL_SIZE,
+};
 
 #[error_code]
 pub enum P2PError {
@@ -72,6 +77,183 @@ pub enum P2PError {
 
     #[msg("Invalid order type")]
     InvalidOrderType,
+
+    #[msg("Invalid payment window")]
+    InvalidPaymentWindow,
+
+    #[msg("Payment window not elapsed")]
+    PaymentWindowNotElapsed,
+
+    #[msg("Token mint mismatch")]
+    InvalidTokenMint,
+
+    #[msg("Invalid auto-release window")]
+    InvalidAutoReleaseWindow,
+
+    #[msg("Auto-release window not elapsed")]
+    AutoReleaseNotDue,
+
+    #[msg("Trade not finished")]
+    TradeNotFinished,
+
+    #[msg("Order not finished")]
+    OrderNotFinished,
+
+    #[msg("Escrow not empty")]
+    EscrowNotEmpty,
+
+    #[msg("Token account required")]
+    MissingTokenAccount,
+
+    #[msg("Escrow token account mismatch")]
+    EscrowTokenAccountMismatch,
+
+    #[msg("Buyer token account mismatch")]
+    BuyerTokenAccountMismatch,
+
+    #[msg("Seller token account mismatch")]
+    SellerTokenAccountMismatch,
+
+    #[msg("Fee token account mismatch")]
+    FeeTokenAccountMismatch,
+
+    #[msg("Bond token account mismatch")]
+    BondTokenAccountMismatch,
+
+    #[msg("Invalid fee rate")]
+    InvalidFeeRate,
+
+    #[msg("Invalid order expiry")]
+    InvalidOrderExpiry,
+
+    #[msg("Not pending authority")]
+    NotPendingAuthority,
+
+    #[msg("Arbitrator already exists")]
+    ArbitratorAlreadyExists,
+
+    #[msg("Arbitrator not found")]
+    ArbitratorNotFound,
+
+    #[msg("Too many arbitrators")]
+    TooManyArbitrators,
+
+    #[msg("No arbitrator available")]
+    NoArbitratorAvailable,
+
+    #[msg("Arbitrator cannot be skipped")]
+    ArbitratorNotSkippable,
+
+    #[msg("Arbitrator has active cases")]
+    ArbitratorHasActiveCases,
+
+    #[msg("Evidence phase closed")]
+    EvidencePhaseClosed,
+
+    #[msg("Evidence label too long")]
+    EvidenceLabelTooLong,
+
+    #[msg("Evidence limit reached")]
+    EvidenceLimitReached,
+
+    #[msg("Response window not elapsed")]
+    ResponseWindowNotElapsed,
+
+    #[msg("Invalid response window")]
+    InvalidResponseWindow,
+
+    #[msg("Dispute not in arbitration")]
+    DisputeNotArbitrating,
+
+    #[msg("Invalid ruling")]
+    InvalidRuling,
+
+    #[msg("Dispute not ruled")]
+    DisputeNotRuled,
+
+    #[msg("Appeal window still open")]
+    AppealWindowOpen,
+
+    #[msg("Appeal window closed")]
+    AppealWindowClosed,
+
+    #[msg("Invalid appeal window")]
+    InvalidAppealWindow,
+
+    #[msg("Appeal not open")]
+    AppealNotOpen,
+
+    #[msg("Appeal not found")]
+    AppealNotFound,
+
+    #[msg("Appeal voting still open")]
+    AppealVotingOpen,
+
+    #[msg("Already voted")]
+    AlreadyVoted,
+
+    #[msg("Invalid appeal panel")]
+    InvalidAppealPanel,
+
+    #[msg("Commit phase closed")]
+    CommitPhaseClosed,
+
+    #[msg("Reveal phase closed")]
+    RevealPhaseClosed,
+
+    #[msg("Vote not committed")]
+    VoteNotCommitted,
+
+    #[msg("Commitment mismatch")]
+    CommitmentMismatch,
+
+    #[msg("Invalid username")]
+    InvalidUsername,
+
+    #[msg("Avatar URL too long")]
+    AvatarTooLong,
+
+    #[msg("Username already set")]
+    UsernameAlreadySet,
+
+    #[msg("Trade not completed")]
+    TradeNotCompleted,
+
+    #[msg("Invalid reputation parameters")]
+    InvalidReputationParams,
+
+    #[msg("Insufficient KYC level")]
+    InsufficientKycLevel,
+
+    #[msg("Verified users only")]
+    VerificationRequired,
+
+    #[msg("Invalid order requirements")]
+    InvalidOrderRequirements,
+
+    #[msg("Duplicate vote commitment")]
+    DuplicateCommitment,
+
+    #[msg("Dispute must be decided by a panel")]
+    PanelReviewRequired,
+
+    #[msg("Dispute does not require a panel")]
+    PanelReviewNotRequired,
+
+    #[msg("Rating window still open")]
+    RatingWindowOpen,
+
+    #[msg("Invalid rating window")]
+    InvalidRatingWindow,
+
+    #[msg("Cannot take own order")]
+    SelfTrade,
+
+    #[msg("Rating window closed")]
+    RatingWindowClosed,
+
+    #[msg("Bond recipient account required")]
+    MissingBondRecipient,
 }
 
 // ============ 事件定义 ============
@@ -79,7 +261,7 @@ pub enum P2PError {
 #[event]
 pub struct PlatformInitialized {
     pub authority: Pubkey,
-    pub platform_fee: u64,
+    pub fee_schedule: [u64; 5],
 }
 
 #[event]
@@ -94,25 +276,32 @@ pub struct OrderCreated {
 #[event]
 pub struct OrderTaken {
     pub order_id: u64,
+    pub trade_id: u64,
     pub taker: Pubkey,
+    pub amount: u64,
+    pub remaining_amount: u64,
 }
 
 #[event]
 pub struct PaymentConfirmed {
     pub order_id: u64,
+    pub trade_id: u64,
     pub payer: Pubkey,
 }
 
 #[event]
 pub struct TokensReleased {
     pub order_id: u64,
+    pub trade_id: u64,
     pub buyer: Pubkey,
     pub amount: u64,
+    pub fee_rate: u64,
 }
 
 #[event]
 pub struct OrderCompleted {
     pub order_id: u64,
+    pub trade_id: u64,
     pub buyer: Pubkey,
     pub seller: Pubkey,
 }
@@ -123,6 +312,25 @@ pub struct OrderCancelled {
     pub canceler: Pubkey,
 }
 
+#[event]
+pub struct TradeCancelled {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub buyer: Pubkey,
+    pub refund_amount: u64,
+}
+
+#[event]
+pub struct TradeClosed {
+    pub order_id: u64,
+    pub trade_id: u64,
+}
+
+#[event]
+pub struct OrderClosed {
+    pub order_id: u64,
+}
+
 #[event]
 pub struct EscrowLocked {
     pub order_id: u64,
@@ -140,14 +348,189 @@ pub struct EscrowReleased {
 #[event]
 pub struct DisputeOpened {
     pub order_id: u64,
+    pub trade_id: u64,
     pub plaintiff: Pubkey,
+    pub arbitrator: Pubkey,
     pub reason: String,
+    pub bond_amount: u64,
+    pub bond_in_sol: bool,
+}
+
+#[event]
+pub struct EvidenceSubmitted {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub submitter: Pubkey,
+    pub label: String,
+    pub hash: [u8; 32],
+}
+
+#[event]
+pub struct ArbitrationStarted {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub arbitrator: Pubkey,
+}
+
+#[event]
+pub struct DisputeRuled {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub arbitrator: Pubkey,
+    pub ruling: Ruling,
+    pub appeal_deadline: i64,
+}
+
+#[event]
+pub struct PanelConvened {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub convener: Pubkey,
+    pub panel: Vec<Pubkey>,
+}
+
+#[event]
+pub struct DisputeAppealed {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub appellant: Pubkey,
+    pub panel: Vec<Pubkey>,
+    pub bond_amount: u64,
+}
+
+#[event]
+pub struct VoteCommitted {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub arbitrator: Pubkey,
+    pub commitment: [u8; 32],
+}
+
+#[event]
+pub struct VoteRevealed {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub arbitrator: Pubkey,
+    pub ruling: Ruling,
+}
+
+#[event]
+pub struct ArbitratorSlashed {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub arbitrator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AppealDecided {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub appellant: Pubkey,
+    pub ruling: Ruling,
+    pub appellant_won: bool,
 }
 
 #[event]
 pub struct DisputeResolved {
     pub order_id: u64,
+    pub trade_id: u64,
     pub ruling: Ruling,
+    pub buyer_amount: u64,
+    pub seller_amount: u64,
+    pub bond_status: BondStatus,
+}
+
+#[event]
+pub struct TreasuryInitialized {
+    pub token_mint: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub token_mint: Pubkey,
+    pub destination: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct RoleUpdated {
+    pub role: Role,
+    pub old: Pubkey,
+    pub new: Pubkey,
+}
+
+#[event]
+pub struct ArbitratorAdded {
+    pub arbitrator: Pubkey,
+    pub max_amount: u64,
+}
+
+#[event]
+pub struct ArbitratorRemoved {
+    pub arbitrator: Pubkey,
+}
+
+#[event]
+pub struct ArbitratorUpdated {
+    pub arbitrator: Pubkey,
+    pub status: ArbitratorStatus,
+    pub max_amount: u64,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub old: ConfigParams,
+    pub new: ConfigParams,
+}
+
+#[event]
+pub struct FeeScheduleUpdated {
+    pub old_schedule: [u64; 5],
+    pub new_schedule: [u64; 5],
+}
+
+#[event]
+pub struct ProfileCreated {
+    pub wallet: Pubkey,
+    pub username: String,
+}
+
+#[event]
+pub struct ProfileUpdated {
+    pub wallet: Pubkey,
+    pub avatar: Option<String>,
+}
+
+#[event]
+pub struct UsernameChanged {
+    pub wallet: Pubkey,
+    pub old_username: String,
+    pub new_username: String,
+}
+
+#[event]
+pub struct CounterpartyRated {
+    pub order_id: u64,
+    pub trade_id: u64,
+    pub rater: Pubkey,
+    pub ratee: Pubkey,
+    pub positive: bool,
+    pub comment_hash: Option<[u8; 32]>,
 }
 
 #[event]
@@ -157,6 +540,21 @@ pub struct ReputationUpdated {
     pub new_score: u32,
 }
 
+#[event]
+pub struct ReputationAdjusted {
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    pub change: i32,
+}
+
+#[event]
+pub struct UserStatusUpdated {
+    pub user: Pubkey,
+    pub kyc_level: u8,
+    pub is_verified: bool,
+    pub is_banned: bool,
+}
+
 // ============ 工具函数 ============
 
 /// 计算手续费
@@ -168,6 +566,116 @@ pub fn calculate_fee(amount: u64, fee_rate: u64) -> u64 {
         .unwrap() as u64
 }
 
+/// 从成交托管放行给买家, 并将手续费转入国库
+pub fn release_escrow<'info>(
+    escrow: &Account<'info, EscrowAccount>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    buyer_token_account: &Account<'info, TokenAccount>,
+    treasury: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    release_amount: u64,
+    fee: u64,
+) -> Result<()> {
+    let order_id_bytes = escrow.order_id.to_le_bytes();
+    let trade_id_bytes = escrow.trade_id.to_le_bytes();
+    let seeds = &[
+        b"escrow",
+        order_id_bytes.as_ref(),
+        trade_id_bytes.as_ref(),
+        &[escrow.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: escrow_token_account.to_account_info(),
+        to: buyer_token_account.to_account_info(),
+        authority: escrow.to_account_info(),
+    };
+    token::transfer(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer),
+        release_amount,
+    )?;
+
+    if fee > 0 {
+        let fee_accounts = Transfer {
+            from: escrow_token_account.to_account_info(),
+            to: treasury.to_account_info(),
+            authority: escrow.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(token_program.to_account_info(), fee_accounts, signer),
+            fee,
+        )?;
+    }
+    Ok(())
+}
+
+/// 完成成交: 先衰减双方信誉, 按挂单方等级收费放行托管, 再累计成交数与信誉
+/// 自动放行时卖家未按时放行, 不计入卖家的完成数与信誉增长; 返回 (放行数量, 费率)
+#[allow(clippy::too_many_arguments)]
+pub fn settle_trade<'info>(
+    trade: &mut Account<'info, Trade>,
+    escrow: &mut Account<'info, EscrowAccount>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    buyer_token_account: &Account<'info, TokenAccount>,
+    treasury: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    config: &PlatformConfig,
+    maker: (&mut UserProfile, &mut ReputationHistory),
+    taker: (&mut UserProfile, &mut ReputationHistory),
+    trade_pair: &mut TradePair,
+    reward_seller: bool,
+) -> Result<(u64, u64)> {
+    let now = Clock::get()?.unix_timestamp;
+    let (maker_profile, maker_history) = maker;
+    let (taker_profile, taker_history) = taker;
+    maker_profile.apply_decay(maker_history, config.reputation_half_life, now);
+    taker_profile.apply_decay(taker_history, config.reputation_half_life, now);
+
+    let fee_rate = config.fee_rate_for_level(maker_profile.get_level());
+    let fee = calculate_fee(trade.amount, fee_rate);
+    let release_amount = trade.amount.checked_sub(fee).unwrap();
+    release_escrow(
+        escrow,
+        escrow_token_account,
+        buyer_token_account,
+        treasury,
+        token_program,
+        release_amount,
+        fee,
+    )?;
+
+    trade.status = OrderStatus::Completed;
+    trade.completed_at = Some(now);
+    escrow.status = EscrowStatus::Released;
+
+    let maker_is_seller = trade.seller() == trade.maker;
+    let gain = config.trade_reputation_gain(trade.amount, trade_pair.completed_trades);
+    for (profile, history, is_seller) in [
+        (maker_profile, maker_history, maker_is_seller),
+        (taker_profile, taker_history, !maker_is_seller),
+    ] {
+        if is_seller && !reward_seller {
+            continue;
+        }
+        profile.completed_trades += 1;
+        let gain = profile.cap_daily_gain(gain, now, config.reputation_daily_cap);
+        if gain > 0 {
+            profile.change_reputation(
+                history,
+                gain as i32,
+                ReputationReason::TradeCompleted,
+                Some(trade.order_id),
+                now,
+            );
+        }
+        profile.update_completion_rate();
+    }
+    trade_pair.completed_trades += 1;
+
+    Ok((release_amount, fee_rate))
+}
+
 /// 验证支付方式
 pub fn validate_payment_method(method: &str) -> bool {
     let valid_methods = [
@@ -215,3 +723,68 @@ pub fn get_level_icon(level: u8) -> &'static str {
         _ => "❓",
     }
 }
+
+/// 从仲裁员名册中按轮询顺序选出上诉合议庭, 跳过原仲裁员及不可受理该金额者;
+/// 入选成员的在办案件数加一, 其账户须可写
+pub fn select_panel<'info>(
+    config: &mut PlatformConfig,
+    candidates: &'info [AccountInfo<'info>],
+    amount: u64,
+    original_arbitrator: &Pubkey,
+) -> Result<Vec<Pubkey>> {
+    let registry_len = config.arbitrators.len();
+    require!(
+        registry_len > 0 && candidates.len() <= registry_len,
+        P2PError::InvalidAppealPanel
+    );
+
+    let mut index = config.arbitrator_cursor as usize % registry_len;
+    let mut panel = Vec::with_capacity(APPEAL_PANEL_SIZE);
+    for info in candidates.iter() {
+        require!(
+            panel.len() < APPEAL_PANEL_SIZE,
+            P2PError::InvalidAppealPanel
+        );
+        let mut candidate = Account::<Arbitrator>::try_from(info)?;
+        require!(
+            candidate.wallet == config.arbitrators[index],
+            P2PError::ArbitratorNotFound
+        );
+        if candidate.wallet != *original_arbitrator && candidate.is_eligible(amount) {
+            candidate.active_cases += 1;
+            candidate.exit(&crate::ID)?;
+            panel.push(candidate.wallet);
+        }
+        index = (index + 1) % registry_len;
+    }
+    require!(
+        panel.len() == APPEAL_PANEL_SIZE,
+        P2PError::NoArbitratorAvailable
+    );
+
+    config.arbitrator_cursor = index as u32;
+    Ok(panel)
+}
+
+/// 合议结束后释放合议庭成员的在办案件, 已揭示投票者计入已结案件;
+/// 成员账户需按合议庭顺序传入
+pub fn settle_panel<'info>(
+    panel: &[Pubkey],
+    votes: &[PanelVote],
+    members: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    require!(members.len() == panel.len(), P2PError::InvalidAppealPanel);
+    for (wallet, info) in panel.iter().zip(members.iter()) {
+        let mut member = Account::<Arbitrator>::try_from(info)?;
+        require!(member.wallet == *wallet, P2PError::ArbitratorNotFound);
+        member.active_cases = member.active_cases.saturating_sub(1);
+        if votes
+            .iter()
+            .any(|v| v.arbitrator == *wallet && v.ruling.is_some())
+        {
+            member.resolved_cases += 1;
+        }
+        member.exit(&crate::ID)?;
+    }
+    Ok(())
+}
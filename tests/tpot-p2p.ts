@@ -101,8 +101,9 @@ describe("TPOT-P2P Trading Platform", () => {
     it("应该成功初始化平台", async () => {
       await program.methods
         .initialize(
-          50,  // 0.5% 平台手续费
-          100  // 1% 争议手续费
+          [50, 50, 30, 10, 10].map((fee) => new anchor.BN(fee)), // 各等级手续费
+          new anchor.BN(100),  // 1% 争议手续费
+          new anchor.BN(86400) // 付款后 24 小时自动放行
         )
         .accounts({
           platformConfig: platformConfigPDA,
@@ -115,12 +116,41 @@ describe("TPOT-P2P Trading Platform", () => {
       const config = await program.account.platformConfig.fetch(platformConfigPDA);
       
       expect(config.authority.toString()).to.equal(authority.publicKey.toString());
-      expect(config.platformFee.toNumber()).to.equal(50);
+      expect(config.feeSchedule[0].toNumber()).to.equal(50);
       expect(config.disputeFee.toNumber()).to.equal(100);
       expect(config.paused).to.be.false;
     });
   });
 
+  // 创建用户资料并登记用户名
+  const createProfile = (user: anchor.web3.Keypair, username: string) => {
+    const [profilePDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("profile"), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const [historyPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("reputation_history"), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const [usernamePDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("username"), Buffer.from(username)],
+      program.programId
+    );
+
+    return program.methods
+      .createProfile(username, null)
+      .accounts({
+        platformConfig: platformConfigPDA,
+        profile: profilePDA,
+        reputationHistory: historyPDA,
+        usernameRecord: usernamePDA,
+        user: user.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+  };
+
   describe("2. 用户资料", () => {
     it("应该成功创建用户资料", async () => {
       const [profilePDA] = anchor.web3.PublicKey.findProgramAddressSync(
@@ -128,22 +158,32 @@ describe("TPOT-P2P Trading Platform", () => {
         program.programId
       );
 
-      await program.methods
-        .createProfile()
-        .accounts({
-          profile: profilePDA,
-          user: buyer.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-        })
-        .signers([buyer])
-        .rpc();
+      await createProfile(buyer, "buyer_one");
 
       const profile = await program.account.userProfile.fetch(profilePDA);
       
       expect(profile.wallet.toString()).to.equal(buyer.publicKey.toString());
+      expect(profile.username).to.equal("buyer_one");
       expect(profile.reputation.toNumber()).to.equal(0);
       expect(profile.totalTrades.toNumber()).to.equal(0);
     });
+
+    it("用户名已被占用时应创建失败", async () => {
+      let failed = false;
+      try {
+        await createProfile(seller, "buyer_one");
+      } catch (err) {
+        failed = true;
+      }
+      expect(failed).to.be.true;
+
+      const [usernamePDA] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("username"), Buffer.from("buyer_one")],
+        program.programId
+      );
+      const record = await program.account.usernameRecord.fetch(usernamePDA);
+      expect(record.owner.toString()).to.equal(buyer.publicKey.toString());
+    });
   });
 
   describe("3. 订单系统", () => {
@@ -172,15 +212,10 @@ describe("TPOT-P2P Trading Platform", () => {
       );
 
       // 先创建卖家资料
-      await program.methods
-        .createProfile()
-        .accounts({
-          profile: sellerProfilePDA,
-          user: seller.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-        })
-        .signers([seller])
-        .rpc();
+      await createProfile(seller, "seller_one");
+
+      const sellerProfile = await program.account.userProfile.fetch(sellerProfilePDA);
+      expect(sellerProfile.username).to.equal("seller_one");
 
       const amount = new anchor.BN(100_000_000_000); // 100 tokens
       const price = new anchor.BN(1_000_000); // 0.001 SOL per token